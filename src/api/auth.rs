@@ -1,19 +1,27 @@
 use std::net::SocketAddr;
 
+use crate::api::menu::{UserMenus, user_menus};
 use crate::app::auth::{Principal, jwt_service};
 use crate::app::error::ApiError;
+use crate::app::middleware::AuthLayer;
+use crate::app::scope::DataPermission;
 use crate::app::util::verify_password;
 use crate::app::{ApiReturn, AppState, extract::ValidJson, response::ApiResponse};
 use crate::entity::prelude::*;
 use crate::entity::sys_user::{self};
+use crate::metrics;
 use axum::extract::ConnectInfo;
-use axum::{Router, extract::State, routing};
+use axum::{Extension, Router, extract::State, routing};
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use serde::Deserialize;
+use tower_http::auth::AsyncRequireAuthorizationLayer;
 use validator::Validate;
 
 pub fn create_router() -> Router<AppState> {
-    Router::new().route("/login", routing::post(login))
+    Router::new().route("/login", routing::post(login)).route(
+        "/menus",
+        routing::get(get_menus).route_layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+    )
 }
 
 #[derive(Clone, Deserialize, Validate)]
@@ -34,7 +42,7 @@ struct LoginParams {
 
 #[tracing::instrument(name = "user_login", skip_all, fields(account = %params.account, ip = %addr.ip()))]
 async fn login(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     ValidJson(params): ValidJson<LoginParams>,
 ) -> ApiReturn<String> {
@@ -45,8 +53,10 @@ async fn login(
         .map_err(|_| ApiError::LoginError)?;
 
     if user.is_none() || !verify_password(&params.password, &user.as_ref().unwrap().password)? {
+        metrics::record_login(false);
         return Err(ApiError::LoginError);
     }
+    metrics::record_login(true);
 
     let user = user.unwrap();
     let principal = Principal {
@@ -56,3 +66,15 @@ async fn login(
 
     Ok(ApiResponse::success(jwt_service().encode(principal)?))
 }
+
+/// The menus and permission keys of the current user, for the front end to
+/// build its navigation and routes from.
+async fn get_menus(
+    State(AppState { db, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    permission: DataPermission,
+) -> ApiReturn<UserMenus> {
+    Ok(ApiResponse::success(
+        user_menus(&db, &principal.id, &permission).await?,
+    ))
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use axum::{Router, extract::State, routing};
+use sea_orm::{
+    ActiveValue, IntoActiveModel, PaginatorTrait, QueryOrder, TransactionTrait,
+    prelude::*,
+    sea_query::{BinOper, Func},
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    app::{
+        ApiReturn, AppState,
+        error::{ApiError, ApiResult},
+        extract::{Path, ValidJson},
+        permission,
+        response::ApiResponse,
+        scope::DataPermission,
+    },
+    entity::{prelude::*, sys_dept, sys_user},
+};
+
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/", routing::get(get_dept_tree).post(create_dept))
+        .route(
+            "/{id}",
+            routing::get(get_dept).put(update_dept).delete(delete_dept),
+        )
+        .route("/{id}/parent", routing::put(move_dept))
+}
+
+/// A department with its descendants nested below it.
+#[derive(Debug, Serialize)]
+pub struct DeptNode {
+    #[serde(flatten)]
+    pub dept: sys_dept::Model,
+    pub children: Vec<DeptNode>,
+}
+
+/// Nests `depts` under their parents. Departments whose parent is not in
+/// `depts` become roots, so a subtree can be built from its rows alone.
+fn build_tree(depts: Vec<sys_dept::Model>) -> Vec<DeptNode> {
+    let ids = depts
+        .iter()
+        .map(|dept| dept.id.clone())
+        .collect::<std::collections::HashSet<_>>();
+    let mut children = HashMap::<Option<String>, Vec<sys_dept::Model>>::new();
+    for dept in depts {
+        let parent = dept.parent_id.clone().filter(|parent| ids.contains(parent));
+        children.entry(parent).or_default().push(dept);
+    }
+
+    fn attach(
+        parent: Option<String>,
+        children: &mut HashMap<Option<String>, Vec<sys_dept::Model>>,
+    ) -> Vec<DeptNode> {
+        children
+            .remove(&parent)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|dept| {
+                let nested = attach(Some(dept.id.clone()), children);
+                DeptNode {
+                    dept,
+                    children: nested,
+                }
+            })
+            .collect()
+    }
+
+    attach(None, &mut children)
+}
+
+fn ordered() -> Select<SysDept> {
+    SysDept::find()
+        .order_by_asc(sys_dept::Column::Sort)
+        .order_by_asc(sys_dept::Column::Name)
+}
+
+pub async fn find_dept<C: ConnectionTrait>(db: &C, dept_id: &str) -> ApiResult<sys_dept::Model> {
+    SysDept::find_by_id(dept_id)
+        .one(db)
+        .await
+        .context("Find department by dept_id")?
+        .ok_or(ApiError::NotFound)
+}
+
+async fn get_dept_tree(State(AppState { db, .. }): State<AppState>) -> ApiReturn<Vec<DeptNode>> {
+    let depts = ordered()
+        .all(&db)
+        .await
+        .context("Failed to fetch departments")?;
+
+    Ok(ApiResponse::success(build_tree(depts)))
+}
+
+async fn get_dept(
+    State(AppState { db, .. }): State<AppState>,
+    Path(dept_id): Path<String>,
+) -> ApiReturn<DeptNode> {
+    let dept = find_dept(&db, &dept_id).await?;
+    let depts = ordered()
+        .filter(sys_dept::subtree(&dept.id))
+        .all(&db)
+        .await
+        .context("Failed to fetch departments")?;
+
+    let node = build_tree(depts)
+        .into_iter()
+        .next()
+        .context("Department missing from its own subtree")?;
+
+    Ok(ApiResponse::success(node))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct DeptParams {
+    parent_id: Option<String>,
+    #[validate(length(
+        min = 1,
+        max = 32,
+        message = "Name must be between 1 and 32 characters long"
+    ))]
+    name: String,
+    #[serde(default)]
+    sort: i32,
+}
+
+async fn create_dept(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    ValidJson(params): ValidJson<DeptParams>,
+) -> ApiReturn<sys_dept::Model> {
+    permission.require(permission::DEPT_EDIT)?;
+    let parent_path = match &params.parent_id {
+        Some(parent_id) => parent_dept(&db, parent_id).await?.path,
+        None => "/".to_string(),
+    };
+
+    let dept = sys_dept::ActiveModel {
+        parent_id: ActiveValue::Set(params.parent_id),
+        name: ActiveValue::Set(params.name),
+        path: ActiveValue::Set(parent_path),
+        sort: ActiveValue::Set(params.sort),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .context("Create department")?;
+
+    Ok(ApiResponse::success(dept))
+}
+
+async fn parent_dept<C: ConnectionTrait>(db: &C, parent_id: &str) -> ApiResult<sys_dept::Model> {
+    SysDept::find_by_id(parent_id)
+        .one(db)
+        .await
+        .context("Find parent department")?
+        .ok_or_else(|| ApiError::ValidationError("parent_id: Department not found".to_string()))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct UpdateDeptParams {
+    #[validate(length(
+        min = 1,
+        max = 32,
+        message = "Name must be between 1 and 32 characters long"
+    ))]
+    name: Option<String>,
+    sort: Option<i32>,
+}
+
+async fn update_dept(
+    State(AppState { db, .. }): State<AppState>,
+    Path(dept_id): Path<String>,
+    permission: DataPermission,
+    ValidJson(params): ValidJson<UpdateDeptParams>,
+) -> ApiReturn<sys_dept::Model> {
+    permission.require(permission::DEPT_EDIT)?;
+    let mut active_model = find_dept(&db, &dept_id).await?.into_active_model();
+
+    if let Some(name) = params.name {
+        active_model.name = ActiveValue::Set(name);
+    }
+    if let Some(sort) = params.sort {
+        active_model.sort = ActiveValue::Set(sort);
+    }
+
+    Ok(ApiResponse::success(
+        active_model
+            .update(&db)
+            .await
+            .context("Update department")?,
+    ))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct MoveDeptParams {
+    parent_id: Option<String>,
+}
+
+/// Moves a department and its subtree under another parent, or to the root
+/// when `parent_id` is null.
+async fn move_dept(
+    State(AppState { db, .. }): State<AppState>,
+    Path(dept_id): Path<String>,
+    permission: DataPermission,
+    ValidJson(params): ValidJson<MoveDeptParams>,
+) -> ApiReturn<sys_dept::Model> {
+    permission.require(permission::DEPT_EDIT)?;
+    let txn = db.begin().await.context("Begin transaction")?;
+    let dept = find_dept(&txn, &dept_id).await?;
+
+    let parent_path = match &params.parent_id {
+        Some(parent_id) => {
+            let parent = parent_dept(&txn, parent_id).await?;
+            // The subtree includes the department itself.
+            if parent.is_within(&dept.id) {
+                return Err(ApiError::Conflict(
+                    "A department cannot be moved under itself or its descendants".to_string(),
+                ));
+            }
+            parent.path
+        }
+        None => "/".to_string(),
+    };
+    let path = format!("{parent_path}{}/", dept.id);
+
+    if path != dept.path {
+        // Swap the old path prefix for the new one across the subtree.
+        let suffix = Func::cust("substr")
+            .arg(Expr::col(sys_dept::Column::Path))
+            .arg(i32::try_from(dept.path.len() + 1).context("Department path too long")?);
+        SysDept::update_many()
+            .col_expr(
+                sys_dept::Column::Path,
+                Expr::val(path).binary(BinOper::Custom("||"), suffix),
+            )
+            .filter(sys_dept::subtree(&dept.id))
+            .exec(&txn)
+            .await
+            .context("Move department subtree")?;
+    }
+
+    let mut active_model = find_dept(&txn, &dept_id).await?.into_active_model();
+    active_model.parent_id = ActiveValue::Set(params.parent_id);
+    let dept = active_model.update(&txn).await.context("Move department")?;
+
+    txn.commit().await.context("Commit transaction")?;
+
+    Ok(ApiResponse::success(dept))
+}
+
+/// Deletes a department that has neither child departments nor users.
+async fn delete_dept(
+    State(AppState { db, .. }): State<AppState>,
+    Path(dept_id): Path<String>,
+    permission: DataPermission,
+) -> ApiReturn<()> {
+    permission.require(permission::DEPT_EDIT)?;
+    let dept = find_dept(&db, &dept_id).await?;
+
+    let children = SysDept::find()
+        .filter(sys_dept::Column::ParentId.eq(&dept.id))
+        .count(&db)
+        .await
+        .context("Count child departments")?;
+    if children > 0 {
+        return Err(ApiError::Conflict(
+            "The department still has child departments".to_string(),
+        ));
+    }
+
+    let users = SysUser::find()
+        .filter(sys_user::Column::DeptId.eq(&dept.id))
+        .count(&db)
+        .await
+        .context("Count department users")?;
+    if users > 0 {
+        return Err(ApiError::Conflict(
+            "The department still has users".to_string(),
+        ));
+    }
+
+    SysDept::delete_by_id(&dept.id)
+        .exec(&db)
+        .await
+        .context("Delete the department")?;
+
+    Ok(ApiResponse::success(()))
+}
@@ -0,0 +1,411 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::{Router, routing};
+use sea_orm::{
+    ActiveValue, Condition, DatabaseTransaction, DeriveIntoActiveModel, Order, prelude::*,
+};
+use serde::Deserialize;
+use serde_with::{DisplayFromStr, serde_as};
+use validator::Validate;
+
+use crate::{
+    app::{
+        ApiReturn, AppState,
+        crud::{ApplyUpdate, CrudHooks, CrudRouter, Operation, update_params},
+        error::{ApiError, ApiResult},
+        extract::Path,
+        params::{Filter, Sortable},
+        permission,
+        response::ApiResponse,
+    },
+    dict::{self, DictItem},
+    entity::{prelude::*, sys_dict_data, sys_dict_type},
+};
+
+/// The public lookup of dictionary entries for the front end.
+pub fn create_router() -> Router<AppState> {
+    Router::new().route("/{type}", routing::get(get_dict))
+}
+
+pub fn create_type_router() -> Router<AppState> {
+    CrudRouter::<SysDictType, DictTypeParams, UpdateDictTypeParams>::new()
+        .filter::<DictTypeFilter>()
+        .hooks(DictTypeHooks)
+        .authorize(|permission, operation| {
+            matches!(operation, Operation::List | Operation::Get)
+                || permission.has(permission::DICT_EDIT)
+        })
+        .build()
+}
+
+pub fn create_data_router() -> Router<AppState> {
+    CrudRouter::<SysDictData, DictDataParams, UpdateDictDataParams>::new()
+        .filter::<DictDataFilter>()
+        .hooks(DictDataHooks)
+        .authorize(|permission, operation| {
+            matches!(operation, Operation::List | Operation::Get)
+                || permission.has(permission::DICT_EDIT)
+        })
+        .build()
+}
+
+async fn get_dict(Path(dict_type): Path<String>) -> ApiReturn<Arc<[DictItem]>> {
+    let items = dict::items(&dict_type).ok_or(ApiError::NotFound)?;
+
+    Ok(ApiResponse::success(items))
+}
+
+/// Rejects deleting a built-in dictionary or its entries, which mirror an enum
+/// that stored data and validation rely on.
+fn check_deletable(dict_type: &str) -> ApiResult<()> {
+    if dict::BUILT_IN.contains(&dict_type) {
+        return Err(ApiError::Conflict(format!(
+            "Dictionary `{dict_type}` is built in"
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects new values for a built-in dictionary and disabling its entries,
+/// which would leave values of its enum unaccepted. Labels and the order of
+/// its entries stay editable.
+fn check_built_in_entry(active_model: &sys_dict_data::ActiveModel, insert: bool) -> ApiResult<()> {
+    let (ActiveValue::Set(dict_type) | ActiveValue::Unchanged(dict_type)) = &active_model.dict_type
+    else {
+        return Ok(());
+    };
+    if !dict::BUILT_IN.contains(&dict_type.as_str()) {
+        return Ok(());
+    }
+
+    let changes_value = insert || active_model.value.is_set();
+    let disables = matches!(active_model.enabled, ActiveValue::Set(false));
+    if changes_value || disables {
+        return Err(ApiError::Conflict(format!(
+            "The values of dictionary `{dict_type}` are built in"
+        )));
+    }
+    Ok(())
+}
+
+impl Sortable for SysDictType {
+    fn sort_column(field: &str) -> Option<sys_dict_type::Column> {
+        match field {
+            "code" => Some(sys_dict_type::Column::Code),
+            "name" => Some(sys_dict_type::Column::Name),
+            "createdAt" => Some(sys_dict_type::Column::CreatedAt),
+            _ => None,
+        }
+    }
+
+    fn default_sort() -> Vec<(sys_dict_type::Column, Order)> {
+        vec![(sys_dict_type::Column::Code, Order::Asc)]
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct DictTypeFilter {
+    keyword: Option<String>,
+}
+
+impl Filter for DictTypeFilter {
+    type Entity = SysDictType;
+
+    fn condition(&self) -> Condition {
+        Condition::all().add_option(self.keyword.as_ref().map(|keyword| {
+            Condition::any()
+                .add(sys_dict_type::Column::Code.contains(keyword))
+                .add(sys_dict_type::Column::Name.contains(keyword))
+        }))
+    }
+}
+
+#[derive(Debug, Deserialize, Validate, DeriveIntoActiveModel)]
+#[sea_orm(active_model = "sys_dict_type::ActiveModel")]
+struct DictTypeParams {
+    #[validate(length(
+        min = 1,
+        max = 32,
+        message = "Code must be between 1 and 32 characters long"
+    ))]
+    code: String,
+    #[validate(length(
+        min = 1,
+        max = 32,
+        message = "Name must be between 1 and 32 characters long"
+    ))]
+    name: String,
+    #[validate(length(max = 255, message = "Remark must be at most 255 characters long"))]
+    remark: Option<String>,
+}
+
+/// The code cannot change, since dictionary data refers to it.
+#[derive(Debug, Deserialize, Validate)]
+struct UpdateDictTypeParams {
+    #[validate(length(
+        min = 1,
+        max = 32,
+        message = "Name must be between 1 and 32 characters long"
+    ))]
+    name: Option<String>,
+    #[validate(length(max = 255, message = "Remark must be at most 255 characters long"))]
+    remark: Option<String>,
+}
+
+impl ApplyUpdate<sys_dict_type::ActiveModel> for UpdateDictTypeParams {
+    fn apply_to(self, active_model: &mut sys_dict_type::ActiveModel) {
+        update_params!(active_model, name, self.name);
+        update_params!(active_model, remark, self.remark.map(Some));
+    }
+}
+
+struct DictTypeHooks;
+
+#[async_trait::async_trait]
+impl CrudHooks<SysDictType> for DictTypeHooks {
+    async fn before_save(
+        &self,
+        txn: &DatabaseTransaction,
+        active_model: &mut sys_dict_type::ActiveModel,
+        insert: bool,
+    ) -> ApiResult<()> {
+        let ActiveValue::Set(code) = &active_model.code else {
+            return Ok(());
+        };
+        if !insert {
+            return Ok(());
+        }
+
+        let exists = SysDictType::find()
+            .filter(sys_dict_type::Column::Code.eq(code))
+            .one(txn)
+            .await
+            .context("Find dictionary type by code")?
+            .is_some();
+        if exists {
+            return Err(ApiError::Conflict(format!(
+                "Dictionary type `{code}` already exists"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Deletes the type's data along with it.
+    async fn before_delete(
+        &self,
+        txn: &DatabaseTransaction,
+        dict_type: &sys_dict_type::Model,
+    ) -> ApiResult<()> {
+        check_deletable(&dict_type.code)?;
+        SysDictData::delete_many()
+            .filter(sys_dict_data::Column::DictType.eq(&dict_type.code))
+            .exec(txn)
+            .await
+            .context("Delete dictionary data of the type")?;
+        Ok(())
+    }
+
+    async fn after_commit(&self, db: &DatabaseConnection) -> ApiResult<()> {
+        Ok(dict::reload(db).await?)
+    }
+}
+
+impl Sortable for SysDictData {
+    fn sort_column(field: &str) -> Option<sys_dict_data::Column> {
+        match field {
+            "dictType" => Some(sys_dict_data::Column::DictType),
+            "value" => Some(sys_dict_data::Column::Value),
+            "label" => Some(sys_dict_data::Column::Label),
+            "sort" => Some(sys_dict_data::Column::Sort),
+            "createdAt" => Some(sys_dict_data::Column::CreatedAt),
+            _ => None,
+        }
+    }
+
+    fn default_sort() -> Vec<(sys_dict_data::Column, Order)> {
+        vec![
+            (sys_dict_data::Column::DictType, Order::Asc),
+            (sys_dict_data::Column::Sort, Order::Asc),
+        ]
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize, Validate)]
+struct DictDataFilter {
+    dict_type: Option<String>,
+    keyword: Option<String>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    enabled: Option<bool>,
+}
+
+impl Filter for DictDataFilter {
+    type Entity = SysDictData;
+
+    fn condition(&self) -> Condition {
+        Condition::all()
+            .add_option(
+                self.dict_type
+                    .as_ref()
+                    .map(|dict_type| sys_dict_data::Column::DictType.eq(dict_type)),
+            )
+            .add_option(self.keyword.as_ref().map(|keyword| {
+                Condition::any()
+                    .add(sys_dict_data::Column::Value.contains(keyword))
+                    .add(sys_dict_data::Column::Label.contains(keyword))
+            }))
+            .add_option(
+                self.enabled
+                    .map(|enabled| sys_dict_data::Column::Enabled.eq(enabled)),
+            )
+    }
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, Validate, DeriveIntoActiveModel)]
+#[sea_orm(active_model = "sys_dict_data::ActiveModel")]
+struct DictDataParams {
+    dict_type: String,
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "Value must be between 1 and 64 characters long"
+    ))]
+    value: String,
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "Label must be between 1 and 64 characters long"
+    ))]
+    label: String,
+    #[serde(default)]
+    sort: i32,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+}
+
+/// The type and value cannot change, since stored data refers to them.
+#[derive(Debug, Deserialize, Validate)]
+struct UpdateDictDataParams {
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "Label must be between 1 and 64 characters long"
+    ))]
+    label: Option<String>,
+    sort: Option<i32>,
+    enabled: Option<bool>,
+}
+
+impl ApplyUpdate<sys_dict_data::ActiveModel> for UpdateDictDataParams {
+    fn apply_to(self, active_model: &mut sys_dict_data::ActiveModel) {
+        update_params!(active_model, label, self.label);
+        update_params!(active_model, sort, self.sort);
+        update_params!(active_model, enabled, self.enabled);
+    }
+}
+
+struct DictDataHooks;
+
+#[async_trait::async_trait]
+impl CrudHooks<SysDictData> for DictDataHooks {
+    async fn before_save(
+        &self,
+        txn: &DatabaseTransaction,
+        active_model: &mut sys_dict_data::ActiveModel,
+        insert: bool,
+    ) -> ApiResult<()> {
+        check_built_in_entry(active_model, insert)?;
+        let (ActiveValue::Set(dict_type), ActiveValue::Set(value)) =
+            (&active_model.dict_type, &active_model.value)
+        else {
+            return Ok(());
+        };
+        if !insert {
+            return Ok(());
+        }
+
+        let type_exists = SysDictType::find()
+            .filter(sys_dict_type::Column::Code.eq(dict_type))
+            .one(txn)
+            .await
+            .context("Find dictionary type by code")?
+            .is_some();
+        if !type_exists {
+            return Err(ApiError::ValidationError(
+                "dict_type: Dictionary type not found".to_string(),
+            ));
+        }
+
+        let value_exists = SysDictData::find()
+            .filter(sys_dict_data::Column::DictType.eq(dict_type))
+            .filter(sys_dict_data::Column::Value.eq(value))
+            .one(txn)
+            .await
+            .context("Find dictionary data by value")?
+            .is_some();
+        if value_exists {
+            return Err(ApiError::Conflict(format!(
+                "Dictionary `{dict_type}` already has the value `{value}`"
+            )));
+        }
+        Ok(())
+    }
+
+    async fn before_delete(
+        &self,
+        _txn: &DatabaseTransaction,
+        data: &sys_dict_data::Model,
+    ) -> ApiResult<()> {
+        check_deletable(&data.dict_type)
+    }
+
+    async fn after_commit(&self, db: &DatabaseConnection) -> ApiResult<()> {
+        Ok(dict::reload(db).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{IntoActiveModel, TransactionTrait};
+
+    use super::*;
+    use crate::database;
+
+    async fn gender_entry(db: &DatabaseConnection) -> sys_dict_data::ActiveModel {
+        SysDictData::find()
+            .filter(sys_dict_data::Column::DictType.eq(dict::GENDER))
+            .one(db)
+            .await
+            .unwrap()
+            .expect("The gender dictionary is seeded")
+            .into_active_model()
+    }
+
+    #[tokio::test]
+    async fn keeps_built_in_entries_enabled() {
+        let db = database::memory().await;
+        dict::init(&db).await.unwrap();
+        // The only connection of the database is taken by the transaction.
+        let (mut disabled, mut relabeled) = (gender_entry(&db).await, gender_entry(&db).await);
+        let txn = db.begin().await.unwrap();
+
+        disabled.enabled = ActiveValue::Set(false);
+        assert!(matches!(
+            DictDataHooks.before_save(&txn, &mut disabled, false).await,
+            Err(ApiError::Conflict(_))
+        ));
+
+        relabeled.label = ActiveValue::Set("Woman".to_string());
+        relabeled.enabled = ActiveValue::Set(true);
+        DictDataHooks
+            .before_save(&txn, &mut relabeled, false)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,753 @@
+use std::{ops::Bound, sync::LazyLock};
+
+use anyhow::Context;
+use axum::{
+    Extension, Router,
+    body::{Body, Bytes},
+    extract::{DefaultBodyLimit, Multipart, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing,
+};
+use axum_extra::{TypedHeader, headers::Range};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use sea_orm::{ActiveValue, Condition, Order, PaginatorTrait, prelude::*};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use tokio_stream::StreamExt;
+use validator::Validate;
+
+use crate::{
+    app::{
+        ApiReturn, AppState,
+        auth::Principal,
+        error::{ApiError, ApiResult},
+        extract::{Path, ValidQuery},
+        params::{Filter, Paged, QuerySpec, Sortable},
+        permission,
+        response::ApiResponse,
+        scope::DataPermission,
+        upload,
+    },
+    config,
+    entity::{prelude::*, sys_file, sys_file_upload},
+    storage::FileStorage,
+};
+
+const TUS_RESUMABLE: &str = "1.0.0";
+const UPLOAD_OFFSET: &str = "upload-offset";
+const UPLOAD_LENGTH: &str = "upload-length";
+const UPLOAD_METADATA: &str = "upload-metadata";
+const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+
+/// Locks held while files sharing a stored copy are looked up or counted and
+/// the copy is written or deleted, so that a delete cannot remove a copy an
+/// upload has just decided to reuse. Digests are spread over the stripes by
+/// their first byte, so unrelated uploads rarely wait on each other.
+static DIGEST_LOCKS: LazyLock<Vec<Mutex<()>>> =
+    LazyLock::new(|| (0..64).map(|_| Mutex::new(())).collect());
+
+fn digest_lock(sha256: &str) -> &'static Mutex<()> {
+    let stripe = u8::from_str_radix(sha256.get(..2).unwrap_or_default(), 16).unwrap_or_default();
+    &DIGEST_LOCKS[usize::from(stripe) % DIGEST_LOCKS.len()]
+}
+
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/",
+            routing::get(get_files)
+                .post(upload_file)
+                // The upload is bounded by `max_file_size` while it is read.
+                .layer(DefaultBodyLimit::disable()),
+        )
+        .route("/{id}", routing::get(get_file).delete(delete_file))
+        .route("/{id}/download", routing::get(download_file))
+        .route("/uploads", routing::post(create_upload))
+        .route(
+            "/uploads/{id}",
+            // Chunks are bounded by what the upload still expects while they
+            // are read.
+            routing::head(get_upload)
+                .patch(upload_chunk)
+                .delete(delete_upload),
+        )
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct FileFilter {
+    keyword: Option<String>,
+    content_type: Option<String>,
+    created_by: Option<String>,
+}
+
+impl Filter for FileFilter {
+    type Entity = SysFile;
+
+    fn condition(&self) -> Condition {
+        Condition::all()
+            .add_option(
+                self.keyword
+                    .as_ref()
+                    .map(|keyword| sys_file::Column::Name.contains(keyword)),
+            )
+            .add_option(
+                self.content_type
+                    .as_ref()
+                    .map(|content_type| sys_file::Column::ContentType.starts_with(content_type)),
+            )
+            .add_option(
+                self.created_by
+                    .as_ref()
+                    .map(|created_by| sys_file::Column::CreatedBy.eq(created_by)),
+            )
+    }
+}
+
+impl Sortable for SysFile {
+    fn sort_column(field: &str) -> Option<sys_file::Column> {
+        match field {
+            "name" => Some(sys_file::Column::Name),
+            "contentType" => Some(sys_file::Column::ContentType),
+            "size" => Some(sys_file::Column::Size),
+            "createdAt" => Some(sys_file::Column::CreatedAt),
+            _ => None,
+        }
+    }
+
+    fn default_sort() -> Vec<(sys_file::Column, Order)> {
+        vec![(sys_file::Column::CreatedAt, Order::Desc)]
+    }
+}
+
+/// Files are private to their uploader, except to principals who may manage
+/// every file.
+fn visible_files(principal: &Principal, permission: &DataPermission) -> Condition {
+    if permission.has(permission::FILE_MANAGE) {
+        Condition::all()
+    } else {
+        Condition::all().add(sys_file::Column::CreatedBy.eq(&principal.id))
+    }
+}
+
+async fn get_files(
+    State(AppState { db, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    permission: DataPermission,
+    ValidQuery(query): ValidQuery<QuerySpec<FileFilter>>,
+) -> ApiReturn<Paged<sys_file::Model>> {
+    let (select, pagination) = query.into_select();
+    let select = select.filter(visible_files(&principal, &permission));
+
+    let files = pagination
+        .fetch(select, sys_file::Column::Id, &db)
+        .await
+        .context("Failed to fetch files")?;
+
+    Ok(ApiResponse::success(files))
+}
+
+async fn get_file(
+    State(AppState { db, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    permission: DataPermission,
+    Path(file_id): Path<String>,
+) -> ApiReturn<sys_file::Model> {
+    let file = find_file(&db, &file_id, visible_files(&principal, &permission)).await?;
+
+    Ok(ApiResponse::success(file))
+}
+
+async fn upload_file(
+    State(AppState { db, storage, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    mut multipart: Multipart,
+) -> ApiReturn<sys_file::Model> {
+    let limit = config::get().storage().max_file_size();
+    let file = upload::read_file(
+        &mut multipart,
+        "file",
+        usize::try_from(limit).unwrap_or(usize::MAX),
+    )
+    .await?;
+    let name = file.file_name.unwrap_or_else(|| "unnamed".to_string());
+
+    let file = store_file(
+        &db,
+        storage.as_ref(),
+        name,
+        file.content_type,
+        file.data,
+        &principal,
+    )
+    .await?;
+
+    Ok(ApiResponse::success(file))
+}
+
+/// Stores the bytes of a new file, reusing the stored copy of an existing
+/// file with the same SHA-256 digest.
+async fn store_file(
+    db: &DatabaseConnection,
+    storage: &dyn FileStorage,
+    name: String,
+    declared_type: Option<String>,
+    data: Bytes,
+    principal: &Principal,
+) -> ApiResult<sys_file::Model> {
+    let sha256 = format!("{:x}", Sha256::digest(&data));
+    // Trust the file's magic bytes over the client, and the client over
+    // the file name.
+    let content_type = infer::get(&data)
+        .map(|kind| kind.mime_type().to_string())
+        .or(declared_type.filter(|declared| !declared.is_empty()))
+        .unwrap_or_else(|| {
+            mime_guess::from_path(&name)
+                .first_or_octet_stream()
+                .to_string()
+        });
+
+    let _lock = digest_lock(&sha256).lock().await;
+    let existing = SysFile::find()
+        .filter(sys_file::Column::Sha256.eq(&sha256))
+        .one(db)
+        .await
+        .context("Find file by sha256")?;
+    let storage_key = match existing {
+        Some(existing) => existing.storage_key,
+        None => {
+            let key = format!("files/{}/{sha256}", &sha256[..2]);
+            storage.put(&key, data.clone()).await?;
+            key
+        }
+    };
+
+    let file = sys_file::ActiveModel {
+        name: ActiveValue::Set(name),
+        content_type: ActiveValue::Set(content_type),
+        size: ActiveValue::Set(data.len() as i64),
+        sha256: ActiveValue::Set(sha256),
+        storage_key: ActiveValue::Set(storage_key),
+        created_by: ActiveValue::Set(principal.id.clone()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .context("Create file")?;
+
+    Ok(file)
+}
+
+/// Finds a file matching `visible`. Files of others are reported as missing
+/// rather than forbidden.
+async fn find_file(
+    db: &DatabaseConnection,
+    file_id: &str,
+    visible: Condition,
+) -> ApiResult<sys_file::Model> {
+    SysFile::find_by_id(file_id)
+        .filter(visible)
+        .one(db)
+        .await
+        .context("Find file by file_id")?
+        .ok_or(ApiError::NotFound)
+}
+
+async fn delete_file(
+    State(AppState { db, storage, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    permission: DataPermission,
+    Path(file_id): Path<String>,
+) -> ApiReturn<()> {
+    let file = find_file(&db, &file_id, visible_files(&principal, &permission)).await?;
+    remove_file(&db, storage.as_ref(), &file).await?;
+
+    Ok(ApiResponse::success(()))
+}
+
+/// Deletes a file, and its stored copy once no other file shares it.
+async fn remove_file(
+    db: &DatabaseConnection,
+    storage: &dyn FileStorage,
+    file: &sys_file::Model,
+) -> ApiResult<()> {
+    let _lock = digest_lock(&file.sha256).lock().await;
+    SysFile::delete_by_id(&file.id)
+        .exec(db)
+        .await
+        .context("Delete the file")?;
+
+    let references = SysFile::find()
+        .filter(sys_file::Column::Sha256.eq(&file.sha256))
+        .count(db)
+        .await
+        .context("Count files by sha256")?;
+    if references == 0 {
+        storage.delete(&file.storage_key).await?;
+    }
+
+    Ok(())
+}
+
+async fn download_file(
+    State(AppState { db, storage, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    permission: DataPermission,
+    Path(file_id): Path<String>,
+    range: Option<TypedHeader<Range>>,
+) -> ApiResult<Response> {
+    let file = find_file(&db, &file_id, visible_files(&principal, &permission)).await?;
+    let size = file.size as u64;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_str(&file.content_type)
+            .unwrap_or(HeaderValue::from_static("application/octet-stream")),
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&format!(
+            "attachment; filename*=UTF-8''{}",
+            percent_encode(&file.name)
+        ))
+        .context("Build Content-Disposition")?,
+    );
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(
+        header::ETAG,
+        HeaderValue::from_str(&format!("\"{}\"", file.sha256)).context("Build ETag")?,
+    );
+
+    match select_range(range.as_ref().map(|TypedHeader(range)| range), size) {
+        Selection::Whole => {}
+        Selection::Part(start, end) => {
+            let data = storage
+                .get_range(&file.storage_key, start, end)
+                .await?
+                .ok_or(ApiError::NotFound)?;
+            headers.insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes {start}-{}/{size}", end - 1))
+                    .context("Build Content-Range")?,
+            );
+            return Ok((StatusCode::PARTIAL_CONTENT, headers, data).into_response());
+        }
+        Selection::Unsatisfiable => {
+            headers.insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes */{size}")).context("Build Content-Range")?,
+            );
+            return Ok((StatusCode::RANGE_NOT_SATISFIABLE, headers).into_response());
+        }
+    }
+
+    let data = storage
+        .get(&file.storage_key)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    Ok((headers, data).into_response())
+}
+
+/// What to serve of a file for the `Range` header of a download.
+#[derive(Debug, PartialEq, Eq)]
+enum Selection {
+    Whole,
+    /// Bytes `start..end`.
+    Part(u64, u64),
+    /// No requested range overlaps the file.
+    Unsatisfiable,
+}
+
+/// Only single ranges are served partially; multiple ranges fall back to the
+/// whole file, which RFC 9110 allows.
+fn select_range(range: Option<&Range>, size: u64) -> Selection {
+    let Some(range) = range else {
+        return Selection::Whole;
+    };
+    let ranges = range
+        .satisfiable_ranges(size)
+        .filter_map(|(start, end)| resolve_range(start, end, size))
+        .collect::<Vec<_>>();
+    match ranges.as_slice() {
+        [] => Selection::Unsatisfiable,
+        [(start, end)] => Selection::Part(*start, *end),
+        _ => Selection::Whole,
+    }
+}
+
+/// Turns a byte range into `start..end` within a file of `size` bytes.
+fn resolve_range(start: Bound<u64>, end: Bound<u64>, size: u64) -> Option<(u64, u64)> {
+    let start = match start {
+        Bound::Included(start) => start,
+        Bound::Excluded(start) => start + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match end {
+        Bound::Included(end) => end.saturating_add(1).min(size),
+        Bound::Excluded(end) => end.min(size),
+        Bound::Unbounded => size,
+    };
+
+    (start < end).then_some((start, end))
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn header_u64(headers: &HeaderMap, name: &'static str) -> ApiResult<u64> {
+    header_str(headers, name)
+        .and_then(|value| value.trim().parse().ok())
+        .ok_or_else(|| ApiError::ValidationError(format!("{name}: missing or invalid header")))
+}
+
+/// Parses `Upload-Metadata`: comma-separated keys with base64 values.
+fn upload_metadata(headers: &HeaderMap, key: &str) -> Option<String> {
+    header_str(headers, UPLOAD_METADATA)?
+        .split(',')
+        .filter_map(|pair| pair.trim().split_once(' '))
+        .find(|(name, _)| *name == key)
+        .and_then(|(_, value)| STANDARD.decode(value.trim()).ok())
+        .and_then(|value| String::from_utf8(value).ok())
+}
+
+fn tus_headers(upload: &sys_file_upload::Model) -> [(&'static str, String); 4] {
+    [
+        ("tus-resumable", TUS_RESUMABLE.to_string()),
+        (UPLOAD_OFFSET, upload.offset.to_string()),
+        (UPLOAD_LENGTH, upload.length.to_string()),
+        ("cache-control", "no-store".to_string()),
+    ]
+}
+
+async fn find_upload(
+    db: &DatabaseConnection,
+    upload_id: &str,
+    principal: &Principal,
+) -> ApiResult<sys_file_upload::Model> {
+    SysFileUpload::find_by_id(upload_id)
+        .filter(sys_file_upload::Column::CreatedBy.eq(&principal.id))
+        .one(db)
+        .await
+        .context("Find upload by upload_id")?
+        .ok_or(ApiError::NotFound)
+}
+
+/// Reads `body` whole, answering `413 Payload Too Large` as soon as it
+/// exceeds `limit` bytes rather than buffering the rest.
+async fn read_body(body: Body, limit: usize) -> ApiResult<Bytes> {
+    let mut stream = body.into_data_stream();
+    let mut data = Vec::new();
+    while let Some(frame) = stream.next().await {
+        let frame = frame.context("Read the request body")?;
+        if data.len() + frame.len() > limit {
+            return Err(ApiError::PayloadTooLarge(limit));
+        }
+        data.extend_from_slice(&frame);
+    }
+    Ok(Bytes::from(data))
+}
+
+/// Starts a resumable upload following the tus creation extension.
+async fn create_upload(
+    State(AppState { db, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let length = header_u64(&headers, UPLOAD_LENGTH)?;
+    let limit = config::get().storage().max_file_size();
+    if length > limit {
+        return Err(ApiError::PayloadTooLarge(
+            usize::try_from(limit).unwrap_or(usize::MAX),
+        ));
+    }
+
+    let upload = sys_file_upload::ActiveModel {
+        name: ActiveValue::Set(
+            upload_metadata(&headers, "filename").unwrap_or_else(|| "unnamed".to_string()),
+        ),
+        content_type: ActiveValue::Set(upload_metadata(&headers, "filetype")),
+        length: ActiveValue::Set(length as i64),
+        offset: ActiveValue::Set(0),
+        parts: ActiveValue::Set(0),
+        created_by: ActiveValue::Set(principal.id.clone()),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .context("Create upload")?;
+
+    let location = format!("/api/files/uploads/{}", upload.id);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION.as_str(), location)],
+        tus_headers(&upload),
+    )
+        .into_response())
+}
+
+async fn get_upload(
+    State(AppState { db, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    Path(upload_id): Path<String>,
+) -> ApiResult<Response> {
+    let upload = find_upload(&db, &upload_id, &principal).await?;
+
+    Ok((StatusCode::OK, tus_headers(&upload)).into_response())
+}
+
+/// Appends a chunk at `Upload-Offset`, completing the file once all bytes
+/// have arrived. The new file's location is returned in `Content-Location`.
+async fn upload_chunk(
+    State(AppState { db, storage, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    Path(upload_id): Path<String>,
+    headers: HeaderMap,
+    body: Body,
+) -> ApiResult<Response> {
+    if header_str(&headers, header::CONTENT_TYPE.as_str()) != Some(OFFSET_OCTET_STREAM) {
+        return Err(ApiError::UnsupportedMediaType(OFFSET_OCTET_STREAM));
+    }
+    let offset = header_u64(&headers, UPLOAD_OFFSET)?;
+    let upload = find_upload(&db, &upload_id, &principal).await?;
+
+    if offset != upload.offset as u64 {
+        return Err(ApiError::Conflict(format!(
+            "Upload-Offset {offset} does not match the current offset {}",
+            upload.offset
+        )));
+    }
+    let remaining = usize::try_from(upload.length - upload.offset).unwrap_or(usize::MAX);
+    let data = read_body(body, remaining).await?;
+
+    if !data.is_empty() {
+        storage
+            .put(&upload::part_key(&upload.id, upload.parts), data.clone())
+            .await?;
+
+        // Advance only from the offset this chunk was written at, so that a
+        // concurrent chunk for the same offset cannot be counted twice.
+        let result = SysFileUpload::update_many()
+            .col_expr(
+                sys_file_upload::Column::Offset,
+                Expr::value(upload.offset + data.len() as i64),
+            )
+            .col_expr(
+                sys_file_upload::Column::Parts,
+                Expr::value(upload.parts + 1),
+            )
+            .filter(sys_file_upload::Column::Id.eq(&upload.id))
+            .filter(sys_file_upload::Column::Offset.eq(upload.offset))
+            .exec(&db)
+            .await
+            .context("Advance upload offset")?;
+        if result.rows_affected == 0 {
+            return Err(ApiError::Conflict(
+                "The upload was advanced concurrently".to_string(),
+            ));
+        }
+    }
+
+    let upload = find_upload(&db, &upload_id, &principal).await?;
+    if upload.offset < upload.length {
+        return Ok((StatusCode::NO_CONTENT, tus_headers(&upload)).into_response());
+    }
+
+    let mut content = Vec::with_capacity(upload.length as usize);
+    for part in 0..upload.parts {
+        let chunk = storage
+            .get(&upload::part_key(&upload.id, part))
+            .await?
+            .with_context(|| format!("Missing part {part} of upload {}", upload.id))?;
+        content.extend_from_slice(&chunk);
+    }
+
+    let file = store_file(
+        &db,
+        storage.as_ref(),
+        upload.name.clone(),
+        upload.content_type.clone(),
+        Bytes::from(content),
+        &principal,
+    )
+    .await?;
+    upload::remove_upload(&db, storage.as_ref(), &upload).await?;
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [(
+            header::CONTENT_LOCATION.as_str(),
+            format!("/api/files/{}", file.id),
+        )],
+        tus_headers(&upload),
+    )
+        .into_response())
+}
+
+async fn delete_upload(
+    State(AppState { db, storage, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    Path(upload_id): Path<String>,
+) -> ApiResult<Response> {
+    let upload = find_upload(&db, &upload_id, &principal).await?;
+    upload::remove_upload(&db, storage.as_ref(), &upload).await?;
+
+    Ok((StatusCode::NO_CONTENT, [("tus-resumable", TUS_RESUMABLE)]).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{database, storage::testing::FakeS3};
+
+    use super::*;
+
+    fn principal(id: &str) -> Principal {
+        Principal {
+            id: id.to_string(),
+            name: id.to_string(),
+        }
+    }
+
+    async fn store(
+        db: &DatabaseConnection,
+        s3: &FakeS3,
+        data: &'static [u8],
+        owner: &str,
+    ) -> sys_file::Model {
+        store_file(
+            db,
+            &s3.storage,
+            "a.txt".to_string(),
+            None,
+            Bytes::from_static(data),
+            &principal(owner),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn stops_reading_a_body_past_the_limit() {
+        let endless = tokio_stream::iter(std::iter::repeat_with(|| {
+            Ok::<_, std::convert::Infallible>(Bytes::from_static(&[0; 1024]))
+        }));
+        assert!(matches!(
+            read_body(Body::from_stream(endless), 4096).await,
+            Err(ApiError::PayloadTooLarge(4096))
+        ));
+
+        let data = read_body(Body::from("chunk"), 5).await.unwrap();
+        assert_eq!(data, "chunk");
+    }
+
+    #[tokio::test]
+    async fn shares_the_stored_copy_until_the_last_file_is_deleted() {
+        let db = database::memory().await;
+        let s3 = FakeS3::start().await;
+
+        let first = store(&db, &s3, b"same", "alice").await;
+        let second = store(&db, &s3, b"same", "bob").await;
+        assert_eq!(first.storage_key, second.storage_key);
+        assert_eq!(s3.keys(), [first.storage_key.as_str()]);
+
+        remove_file(&db, &s3.storage, &first).await.unwrap();
+        assert_eq!(s3.keys(), [first.storage_key.as_str()]);
+        remove_file(&db, &s3.storage, &second).await.unwrap();
+        assert!(s3.keys().is_empty());
+    }
+
+    #[tokio::test]
+    async fn concurrent_uploads_keep_the_copy_of_a_deleted_file() {
+        let db = database::memory().await;
+        let s3 = FakeS3::start().await;
+
+        let bob = principal("bob");
+        for _ in 0..20 {
+            let file = store(&db, &s3, b"contended", "alice").await;
+            let (removed, stored) = tokio::join!(
+                remove_file(&db, &s3.storage, &file),
+                store_file(
+                    &db,
+                    &s3.storage,
+                    "b.txt".to_string(),
+                    None,
+                    Bytes::from_static(b"contended"),
+                    &bob,
+                ),
+            );
+            removed.unwrap();
+            let stored = stored.unwrap();
+            assert!(
+                s3.storage.get(&stored.storage_key).await.unwrap().is_some(),
+                "The copy of a stored file was deleted"
+            );
+            remove_file(&db, &s3.storage, &stored).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn hides_the_files_of_others() {
+        let db = database::memory().await;
+        let s3 = FakeS3::start().await;
+        let file = store(&db, &s3, b"private", "alice").await;
+        let of = |id: &str| Condition::all().add(sys_file::Column::CreatedBy.eq(id));
+
+        assert!(find_file(&db, &file.id, of("alice")).await.is_ok());
+        assert!(matches!(
+            find_file(&db, &file.id, of("bob")).await,
+            Err(ApiError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn resolves_byte_ranges() {
+        use Bound::*;
+
+        assert_eq!(resolve_range(Included(0), Included(9), 100), Some((0, 10)));
+        assert_eq!(resolve_range(Included(90), Unbounded, 100), Some((90, 100)));
+        assert_eq!(
+            resolve_range(Included(50), Included(500), 100),
+            Some((50, 100))
+        );
+        assert_eq!(resolve_range(Included(100), Unbounded, 100), None);
+    }
+
+    #[test]
+    fn refuses_ranges_past_the_end() {
+        fn range(bounds: impl std::ops::RangeBounds<u64>) -> Range {
+            Range::bytes(bounds).unwrap()
+        }
+
+        assert_eq!(select_range(None, 100), Selection::Whole);
+        assert_eq!(
+            select_range(Some(&range(10..20)), 100),
+            Selection::Part(10, 20)
+        );
+        assert_eq!(
+            select_range(Some(&range(999999..)), 100),
+            Selection::Unsatisfiable
+        );
+        assert_eq!(
+            select_range(Some(&range(100..)), 100),
+            Selection::Unsatisfiable
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use axum::{Json, Router, extract::State, http::StatusCode, routing};
+use serde_json::{Value, json};
+
+use crate::{
+    app::AppState,
+    health::{self, Report, Status},
+};
+
+/// Probes for orchestrators, outside `/api` so that they need no token.
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/live", routing::get(live))
+        .route("/ready", routing::get(ready))
+}
+
+/// Answers as long as the process serves requests at all.
+async fn live() -> Json<Value> {
+    Json(json!({ "status": Status::Up }))
+}
+
+/// Runs every readiness check, answering `503 Service Unavailable` if any
+/// fails so that the instance is taken out of rotation.
+async fn ready(State(state): State<AppState>) -> (StatusCode, Json<Report>) {
+    let report = health::check(&state).await;
+    let status = match report.status {
+        Status::Up => StatusCode::OK,
+        Status::Down => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (status, Json(report))
+}
@@ -0,0 +1,202 @@
+use anyhow::Context;
+use axum::{Router, extract::State, routing};
+use chrono::Utc;
+use sea_orm::{ActiveValue, IntoActiveModel, QueryOrder, prelude::*};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    app::{
+        ApiReturn, AppState,
+        error::{ApiError, ApiResult},
+        extract::{Path, ValidJson, ValidQuery},
+        params::{Paged, Pagination},
+        permission,
+        response::ApiResponse,
+        scope::DataPermission,
+    },
+    entity::{job_status::JobTrigger, prelude::*, sys_job, sys_job_run},
+    job::{self, scheduler},
+};
+
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/", routing::get(get_jobs))
+        .route("/{id}", routing::get(get_job).put(update_job))
+        .route("/{id}/pause", routing::put(pause_job))
+        .route("/{id}/resume", routing::put(resume_job))
+        .route("/{id}/run", routing::post(run_job))
+        .route("/{id}/runs", routing::get(get_job_runs))
+}
+
+#[derive(Debug, Serialize)]
+struct JobView {
+    #[serde(flatten)]
+    job: sys_job::Model,
+    /// Whether a handler for the job's code exists in this build.
+    registered: bool,
+    /// Whether the job is running in this instance.
+    running: bool,
+}
+
+impl From<sys_job::Model> for JobView {
+    fn from(job: sys_job::Model) -> Self {
+        let scheduler = scheduler();
+        Self {
+            registered: scheduler.is_registered(&job.code),
+            running: scheduler.is_running(&job.code),
+            job,
+        }
+    }
+}
+
+async fn find_job(db: &DatabaseConnection, job_id: &str) -> ApiResult<sys_job::Model> {
+    SysJob::find_by_id(job_id)
+        .one(db)
+        .await
+        .context("Find job by id")?
+        .ok_or(ApiError::NotFound)
+}
+
+async fn get_jobs(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+) -> ApiReturn<Vec<JobView>> {
+    permission.require(permission::JOB_MANAGE)?;
+    let jobs = SysJob::find()
+        .order_by_asc(sys_job::Column::Code)
+        .all(&db)
+        .await
+        .context("Failed to fetch jobs")?;
+
+    Ok(ApiResponse::success(
+        jobs.into_iter().map(JobView::from).collect(),
+    ))
+}
+
+async fn get_job(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    Path(job_id): Path<String>,
+) -> ApiReturn<JobView> {
+    permission.require(permission::JOB_MANAGE)?;
+
+    Ok(ApiResponse::success(find_job(&db, &job_id).await?.into()))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct UpdateJobParams {
+    cron: Option<String>,
+    #[validate(length(max = 255, message = "Remark must be at most 255 characters long"))]
+    remark: Option<String>,
+}
+
+/// Changes the schedule of a job, effective from now.
+async fn update_job(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    Path(job_id): Path<String>,
+    ValidJson(params): ValidJson<UpdateJobParams>,
+) -> ApiReturn<JobView> {
+    permission.require(permission::JOB_MANAGE)?;
+    let job = find_job(&db, &job_id).await?;
+    let enabled = job.enabled;
+    let mut active_model = job.into_active_model();
+
+    if let Some(cron) = params.cron {
+        job::parse_cron(&cron).map_err(ApiError::ValidationError)?;
+        if enabled {
+            active_model.next_run_at =
+                ActiveValue::Set(job::next_run(&cron, Utc::now().naive_utc()));
+        }
+        active_model.cron = ActiveValue::Set(cron);
+    }
+    if let Some(remark) = params.remark {
+        active_model.remark = ActiveValue::Set(Some(remark));
+    }
+
+    let job = active_model.update(&db).await.context("Update job")?;
+    scheduler().reload();
+
+    Ok(ApiResponse::success(job.into()))
+}
+
+/// Stops running a job on schedule. A run in progress is not interrupted.
+async fn pause_job(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    Path(job_id): Path<String>,
+) -> ApiReturn<JobView> {
+    permission.require(permission::JOB_MANAGE)?;
+    let mut active_model = find_job(&db, &job_id).await?.into_active_model();
+
+    active_model.enabled = ActiveValue::Set(false);
+    active_model.next_run_at = ActiveValue::Set(None);
+    let job = active_model.update(&db).await.context("Pause job")?;
+    scheduler().reload();
+
+    Ok(ApiResponse::success(job.into()))
+}
+
+/// Runs a paused job on schedule again, from its next occurrence.
+async fn resume_job(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    Path(job_id): Path<String>,
+) -> ApiReturn<JobView> {
+    permission.require(permission::JOB_MANAGE)?;
+    let job = find_job(&db, &job_id).await?;
+    let next_run_at = job::next_run(&job.cron, Utc::now().naive_utc());
+    let mut active_model = job.into_active_model();
+
+    active_model.enabled = ActiveValue::Set(true);
+    active_model.next_run_at = ActiveValue::Set(next_run_at);
+    let job = active_model.update(&db).await.context("Resume job")?;
+    scheduler().reload();
+
+    Ok(ApiResponse::success(job.into()))
+}
+
+/// Runs a job now, whether or not it is paused, returning the started run.
+async fn run_job(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    Path(job_id): Path<String>,
+) -> ApiReturn<sys_job_run::Model> {
+    permission.require(permission::JOB_MANAGE)?;
+    let job = find_job(&db, &job_id).await?;
+    if !scheduler().is_registered(&job.code) {
+        return Err(ApiError::ValidationError(format!(
+            "id: No handler is registered for job `{}`",
+            job.code
+        )));
+    }
+
+    let run = scheduler()
+        .trigger(&job, JobTrigger::Manual)
+        .await?
+        .ok_or_else(|| ApiError::Conflict(format!("Job `{}` is already running", job.code)))?;
+
+    Ok(ApiResponse::success(run))
+}
+
+/// Lists the runs of a job, newest first.
+async fn get_job_runs(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    Path(job_id): Path<String>,
+    ValidQuery(pagination): ValidQuery<Pagination>,
+) -> ApiReturn<Paged<sys_job_run::Model>> {
+    permission.require(permission::JOB_MANAGE)?;
+    let job = find_job(&db, &job_id).await?;
+    let select = SysJobRun::find()
+        .filter(sys_job_run::Column::JobId.eq(job.id))
+        .order_by_desc(sys_job_run::Column::Id);
+
+    let runs = pagination
+        .fetch(select, sys_job_run::Column::Id, &db)
+        .await
+        .context("Failed to fetch job runs")?;
+
+    Ok(ApiResponse::success(runs))
+}
@@ -0,0 +1,373 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Context;
+use axum::{Router, extract::State, routing};
+use sea_orm::{
+    ActiveValue, Condition, DatabaseTransaction, DeriveIntoActiveModel, Order, PaginatorTrait,
+    QueryOrder, prelude::*, sea_query,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    app::{
+        ApiReturn, AppState,
+        crud::{ApplyUpdate, CrudHooks, CrudRouter, Operation, update_params},
+        error::{ApiError, ApiResult},
+        params::{Filter, Sortable},
+        patch::{ApplyPatch, Patch},
+        permission,
+        response::ApiResponse,
+        scope::DataPermission,
+    },
+    entity::{
+        menu_type::MenuType,
+        prelude::*,
+        sys_menu::{self, ActiveModel},
+        sys_role_menu, sys_user_role,
+    },
+};
+
+pub fn create_router() -> Router<AppState> {
+    CrudRouter::<SysMenu, MenuParams, UpdateMenuParams>::new()
+        .filter::<MenuFilter>()
+        .hooks(MenuHooks)
+        .authorize(|permission, operation| {
+            matches!(operation, Operation::List | Operation::Get)
+                || permission.has(permission::MENU_EDIT)
+        })
+        .build()
+        .route("/tree", routing::get(get_menu_tree))
+}
+
+/// A menu with its children nested below it.
+#[derive(Debug, Serialize)]
+pub struct MenuNode {
+    #[serde(flatten)]
+    pub menu: sys_menu::Model,
+    pub children: Vec<MenuNode>,
+}
+
+/// Nests `menus` under their parents. Menus whose parent is not in `menus`
+/// become roots.
+fn build_tree(menus: Vec<sys_menu::Model>) -> Vec<MenuNode> {
+    let ids = menus
+        .iter()
+        .map(|menu| menu.id.clone())
+        .collect::<HashSet<_>>();
+    let mut children = HashMap::<Option<String>, Vec<sys_menu::Model>>::new();
+    for menu in menus {
+        let parent = menu.parent_id.clone().filter(|parent| ids.contains(parent));
+        children.entry(parent).or_default().push(menu);
+    }
+
+    fn attach(
+        parent: Option<String>,
+        children: &mut HashMap<Option<String>, Vec<sys_menu::Model>>,
+    ) -> Vec<MenuNode> {
+        children
+            .remove(&parent)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|menu| {
+                let nested = attach(Some(menu.id.clone()), children);
+                MenuNode {
+                    menu,
+                    children: nested,
+                }
+            })
+            .collect()
+    }
+
+    attach(None, &mut children)
+}
+
+fn ordered() -> Select<SysMenu> {
+    SysMenu::find()
+        .order_by_asc(sys_menu::Column::Sort)
+        .order_by_asc(sys_menu::Column::Name)
+}
+
+async fn get_menu_tree(State(AppState { db, .. }): State<AppState>) -> ApiReturn<Vec<MenuNode>> {
+    let menus = ordered().all(&db).await.context("Failed to fetch menus")?;
+
+    Ok(ApiResponse::success(build_tree(menus)))
+}
+
+/// What a user may reach in the front end.
+#[derive(Debug, Serialize)]
+pub struct UserMenus {
+    /// The directories and pages granted to the user, with the directories
+    /// leading to them. Hidden pages are included so they stay routable.
+    pub menus: Vec<MenuNode>,
+    /// The permission keys of every granted menu, buttons included.
+    pub permissions: Vec<String>,
+}
+
+/// Resolves the menus granted to a user through their roles, or every menu
+/// when `permission` holds all permissions, so that the front end shows what
+/// the API lets the user reach.
+pub async fn user_menus(
+    db: &DatabaseConnection,
+    user_id: &str,
+    permission: &DataPermission,
+) -> ApiResult<UserMenus> {
+    let menus = ordered().all(db).await.context("Failed to fetch menus")?;
+    if permission.has(permission::ALL) {
+        let menus = menus
+            .into_iter()
+            .filter(|menu| menu.r#type != MenuType::Button)
+            .collect();
+        return Ok(UserMenus {
+            menus: build_tree(menus),
+            permissions: vec![permission::ALL.to_string()],
+        });
+    }
+
+    let granted = SysRoleMenu::find()
+        .filter(
+            sys_role_menu::Column::RoleId.in_subquery(
+                sea_query::Query::select()
+                    .column(sys_user_role::Column::RoleId)
+                    .from(SysUserRole)
+                    .and_where(sys_user_role::Column::UserId.eq(user_id))
+                    .to_owned(),
+            ),
+        )
+        .all(db)
+        .await
+        .context("Fetch menus of the user's roles")?
+        .into_iter()
+        .map(|role_menu| role_menu.menu_id)
+        .collect::<HashSet<_>>();
+
+    let parents = menus
+        .iter()
+        .map(|menu| (menu.id.as_str(), menu.parent_id.as_deref()))
+        .collect::<HashMap<_, _>>();
+
+    // A granted page is unreachable without the directories above it.
+    let mut reachable = HashSet::new();
+    for id in &granted {
+        let mut current = Some(id.as_str());
+        while let Some(id) = current {
+            if !reachable.insert(id.to_string()) {
+                break;
+            }
+            current = parents.get(id).copied().flatten();
+        }
+    }
+
+    let mut permissions = permission
+        .permissions()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    permissions.sort();
+
+    let menus = menus
+        .into_iter()
+        .filter(|menu| menu.r#type != MenuType::Button && reachable.contains(&menu.id))
+        .collect();
+
+    Ok(UserMenus {
+        menus: build_tree(menus),
+        permissions,
+    })
+}
+
+impl Sortable for SysMenu {
+    fn sort_column(field: &str) -> Option<sys_menu::Column> {
+        match field {
+            "name" => Some(sys_menu::Column::Name),
+            "sort" => Some(sys_menu::Column::Sort),
+            "createdAt" => Some(sys_menu::Column::CreatedAt),
+            _ => None,
+        }
+    }
+
+    fn default_sort() -> Vec<(sys_menu::Column, Order)> {
+        vec![
+            (sys_menu::Column::Sort, Order::Asc),
+            (sys_menu::Column::Name, Order::Asc),
+        ]
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct MenuFilter {
+    keyword: Option<String>,
+    parent_id: Option<String>,
+    r#type: Option<MenuType>,
+}
+
+impl Filter for MenuFilter {
+    type Entity = SysMenu;
+
+    fn condition(&self) -> Condition {
+        Condition::all()
+            .add_option(self.keyword.as_ref().map(|keyword| {
+                Condition::any()
+                    .add(sys_menu::Column::Name.contains(keyword))
+                    .add(sys_menu::Column::Path.contains(keyword))
+                    .add(sys_menu::Column::Permission.contains(keyword))
+            }))
+            .add_option(
+                self.parent_id
+                    .as_ref()
+                    .map(|parent_id| sys_menu::Column::ParentId.eq(parent_id)),
+            )
+            .add_option(self.r#type.map(|r#type| sys_menu::Column::Type.eq(r#type)))
+    }
+}
+
+fn visible_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, Validate, DeriveIntoActiveModel)]
+struct MenuParams {
+    parent_id: Option<String>,
+    #[validate(length(
+        min = 1,
+        max = 32,
+        message = "Name must be between 1 and 32 characters long"
+    ))]
+    name: String,
+    r#type: MenuType,
+    #[validate(length(max = 255, message = "Path must be at most 255 characters long"))]
+    path: Option<String>,
+    #[validate(length(max = 255, message = "Component must be at most 255 characters long"))]
+    component: Option<String>,
+    #[validate(length(max = 64, message = "Permission must be at most 64 characters long"))]
+    permission: Option<String>,
+    #[validate(length(max = 64, message = "Icon must be at most 64 characters long"))]
+    icon: Option<String>,
+    #[serde(default)]
+    sort: i32,
+    #[serde(default = "visible_by_default")]
+    visible: bool,
+}
+
+/// `null` clears the optional fields, and moves the menu to the root for
+/// `parent_id`. The type cannot change, since children depend on it.
+#[derive(Debug, Deserialize, Validate)]
+struct UpdateMenuParams {
+    #[serde(default)]
+    parent_id: Patch<String>,
+    #[validate(length(
+        min = 1,
+        max = 32,
+        message = "Name must be between 1 and 32 characters long"
+    ))]
+    name: Option<String>,
+    #[serde(default)]
+    #[validate(length(max = 255, message = "Path must be at most 255 characters long"))]
+    path: Patch<String>,
+    #[serde(default)]
+    #[validate(length(max = 255, message = "Component must be at most 255 characters long"))]
+    component: Patch<String>,
+    #[serde(default)]
+    #[validate(length(max = 64, message = "Permission must be at most 64 characters long"))]
+    permission: Patch<String>,
+    #[serde(default)]
+    #[validate(length(max = 64, message = "Icon must be at most 64 characters long"))]
+    icon: Patch<String>,
+    sort: Option<i32>,
+    visible: Option<bool>,
+}
+
+impl ApplyUpdate<ActiveModel> for UpdateMenuParams {
+    fn apply_to(self, active_model: &mut ActiveModel) {
+        // Patches of optional columns accept `null`, so applying them cannot
+        // fail.
+        let _ = self
+            .parent_id
+            .apply("parent_id", &mut active_model.parent_id);
+        let _ = self.path.apply("path", &mut active_model.path);
+        let _ = self
+            .component
+            .apply("component", &mut active_model.component);
+        let _ = self
+            .permission
+            .apply("permission", &mut active_model.permission);
+        let _ = self.icon.apply("icon", &mut active_model.icon);
+        update_params!(active_model, name, self.name);
+        update_params!(active_model, sort, self.sort);
+        update_params!(active_model, visible, self.visible);
+    }
+}
+
+struct MenuHooks;
+
+#[async_trait::async_trait]
+impl CrudHooks<SysMenu> for MenuHooks {
+    /// Checks that a new parent exists, is not a button, and for updates is
+    /// not the menu itself or below it.
+    async fn before_save(
+        &self,
+        txn: &DatabaseTransaction,
+        active_model: &mut ActiveModel,
+        insert: bool,
+    ) -> ApiResult<()> {
+        let ActiveValue::Set(Some(parent_id)) = &active_model.parent_id else {
+            return Ok(());
+        };
+
+        let parent = SysMenu::find_by_id(parent_id)
+            .one(txn)
+            .await
+            .context("Find parent menu")?
+            .ok_or_else(|| ApiError::ValidationError("parent_id: Menu not found".to_string()))?;
+        if parent.r#type == MenuType::Button {
+            return Err(ApiError::ValidationError(
+                "parent_id: Buttons cannot have children".to_string(),
+            ));
+        }
+        if insert {
+            return Ok(());
+        }
+
+        let id = active_model.id.as_ref();
+        let mut ancestor = Some(parent);
+        while let Some(menu) = ancestor {
+            if &menu.id == id {
+                return Err(ApiError::Conflict(
+                    "A menu cannot be moved under itself or its descendants".to_string(),
+                ));
+            }
+            ancestor = match menu.parent_id {
+                Some(parent_id) => SysMenu::find_by_id(parent_id)
+                    .one(txn)
+                    .await
+                    .context("Find ancestor menu")?,
+                None => None,
+            };
+        }
+        Ok(())
+    }
+
+    /// Deletes a menu without children, revoking it from its roles.
+    async fn before_delete(
+        &self,
+        txn: &DatabaseTransaction,
+        menu: &sys_menu::Model,
+    ) -> ApiResult<()> {
+        let children = SysMenu::find()
+            .filter(sys_menu::Column::ParentId.eq(&menu.id))
+            .count(txn)
+            .await
+            .context("Count child menus")?;
+        if children > 0 {
+            return Err(ApiError::Conflict(
+                "The menu still has child menus".to_string(),
+            ));
+        }
+
+        SysRoleMenu::delete_many()
+            .filter(sys_role_menu::Column::MenuId.eq(&menu.id))
+            .exec(txn)
+            .await
+            .context("Revoke the menu from roles")?;
+        Ok(())
+    }
+}
@@ -12,7 +12,19 @@ use crate::{
 };
 
 mod auth;
+mod dept;
+mod dict;
+mod file;
+mod health;
+mod job;
+mod menu;
+mod notification;
+mod push;
+mod role;
+mod setting;
+mod task;
 mod user;
+mod webhook;
 
 pub fn create_router() -> Router<AppState> {
     Router::new()
@@ -23,12 +35,62 @@ pub fn create_router() -> Router<AppState> {
                     "/users",
                     user::create_router().layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
                 )
+                .nest(
+                    "/depts",
+                    dept::create_router().layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+                )
+                .nest(
+                    "/roles",
+                    role::create_router().layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+                )
+                .nest(
+                    "/dict-types",
+                    dict::create_type_router()
+                        .layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+                )
+                .nest(
+                    "/dict-data",
+                    dict::create_data_router()
+                        .layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+                )
+                .nest(
+                    "/jobs",
+                    job::create_router().layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+                )
+                .nest(
+                    "/tasks",
+                    task::create_router().layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+                )
+                .nest(
+                    "/webhooks",
+                    webhook::create_router().layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+                )
+                .nest(
+                    "/menus",
+                    menu::create_router().layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+                )
+                .nest(
+                    "/notifications",
+                    notification::create_router()
+                        .layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+                )
+                .nest(
+                    "/settings",
+                    setting::create_router().layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+                )
+                .nest(
+                    "/files",
+                    file::create_router().layer(AsyncRequireAuthorizationLayer::new(AuthLayer)),
+                )
+                .merge(push::create_router().layer(AsyncRequireAuthorizationLayer::new(AuthLayer)))
+                .nest("/dicts", dict::create_router())
                 .nest("/auth", auth::create_router())
                 .fallback(async || -> ApiResult<()> {
                     warn!("Not Found");
                     Err(ApiError::NotFound)
                 }),
         )
+        .nest("/health", health::create_router())
         .nest(
             "/static",
             Router::new().route(
@@ -0,0 +1,202 @@
+use anyhow::Context;
+use axum::{Extension, Router, extract::State, routing};
+use sea_orm::{Condition, Order, PaginatorTrait, prelude::*, sea_query::Expr};
+use serde::Deserialize;
+use serde_with::{DisplayFromStr, serde_as};
+use validator::Validate;
+
+use crate::{
+    app::{
+        ApiReturn, AppState,
+        auth::Principal,
+        error::ApiError,
+        extract::{Path, ValidJson, ValidQuery},
+        params::{Filter, Paged, QuerySpec, Sortable},
+        permission,
+        response::ApiResponse,
+        scope::DataPermission,
+    },
+    entity::{prelude::*, sys_notification, sys_task},
+    queue::{
+        self,
+        notification::{Announce, Announcement},
+    },
+};
+
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/", routing::get(get_notifications))
+        .route("/unread-count", routing::get(get_unread_count))
+        .route("/read", routing::put(mark_all_read))
+        .route("/{id}/read", routing::put(mark_read))
+        .route("/announcements", routing::post(announce))
+}
+
+/// The notifications of the principal.
+fn inbox(principal: &Principal) -> Select<SysNotification> {
+    SysNotification::find().filter(sys_notification::Column::UserId.eq(&principal.id))
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize, Validate)]
+struct NotificationFilter {
+    /// Only unread notifications when `true`, only read ones when `false`.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    unread: Option<bool>,
+    category: Option<String>,
+}
+
+impl Filter for NotificationFilter {
+    type Entity = SysNotification;
+
+    fn condition(&self) -> Condition {
+        Condition::all()
+            .add_option(self.unread.map(|unread| {
+                if unread {
+                    sys_notification::Column::ReadAt.is_null()
+                } else {
+                    sys_notification::Column::ReadAt.is_not_null()
+                }
+            }))
+            .add_option(
+                self.category
+                    .as_ref()
+                    .map(|category| sys_notification::Column::Category.eq(category)),
+            )
+    }
+}
+
+impl Sortable for SysNotification {
+    fn sort_column(field: &str) -> Option<sys_notification::Column> {
+        match field {
+            "category" => Some(sys_notification::Column::Category),
+            "readAt" => Some(sys_notification::Column::ReadAt),
+            "createdAt" => Some(sys_notification::Column::CreatedAt),
+            _ => None,
+        }
+    }
+
+    fn default_sort() -> Vec<(sys_notification::Column, Order)> {
+        vec![(sys_notification::Column::Id, Order::Desc)]
+    }
+}
+
+/// Lists the principal's notifications, newest first unless sorted.
+async fn get_notifications(
+    State(AppState { db, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    ValidQuery(query): ValidQuery<QuerySpec<NotificationFilter>>,
+) -> ApiReturn<Paged<sys_notification::Model>> {
+    let (select, pagination) = query.into_select();
+    let select = select.filter(sys_notification::Column::UserId.eq(&principal.id));
+
+    let notifications = pagination
+        .fetch(select, sys_notification::Column::Id, &db)
+        .await
+        .context("Failed to fetch notifications")?;
+
+    Ok(ApiResponse::success(notifications))
+}
+
+async fn get_unread_count(
+    State(AppState { db, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+) -> ApiReturn<u64> {
+    let count = inbox(&principal)
+        .filter(sys_notification::Column::ReadAt.is_null())
+        .count(&db)
+        .await
+        .context("Failed to count unread notifications")?;
+
+    Ok(ApiResponse::success(count))
+}
+
+async fn mark_read(
+    State(AppState { db, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    Path(notification_id): Path<String>,
+) -> ApiReturn<()> {
+    let notification = inbox(&principal)
+        .filter(sys_notification::Column::Id.eq(notification_id))
+        .one(&db)
+        .await
+        .context("Find notification by id")?
+        .ok_or(ApiError::NotFound)?;
+
+    // Keeps the time it was first read.
+    if notification.read_at.is_none() {
+        SysNotification::update_many()
+            .col_expr(
+                sys_notification::Column::ReadAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(sys_notification::Column::Id.eq(notification.id))
+            .exec(&db)
+            .await
+            .context("Mark notification read")?;
+    }
+
+    Ok(ApiResponse::success(()))
+}
+
+/// Marks every unread notification of the principal read, returning how
+/// many there were.
+async fn mark_all_read(
+    State(AppState { db, .. }): State<AppState>,
+    Extension(principal): Extension<Principal>,
+) -> ApiReturn<u64> {
+    let result = SysNotification::update_many()
+        .col_expr(
+            sys_notification::Column::ReadAt,
+            Expr::value(chrono::Utc::now().naive_utc()),
+        )
+        .filter(sys_notification::Column::UserId.eq(&principal.id))
+        .filter(sys_notification::Column::ReadAt.is_null())
+        .exec(&db)
+        .await
+        .context("Mark notifications read")?;
+
+    Ok(ApiResponse::success(result.rows_affected))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct AnnouncementParams {
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "Title must be between 1 and 64 characters long"
+    ))]
+    title: String,
+    #[validate(length(
+        min = 1,
+        max = 2000,
+        message = "Content must be between 1 and 2000 characters long"
+    ))]
+    content: String,
+    /// The recipients, or every user when absent.
+    user_ids: Option<Vec<String>>,
+}
+
+/// Queues an announcement to the inbox of the given users or of everyone,
+/// returning the task that delivers it.
+async fn announce(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    ValidJson(params): ValidJson<AnnouncementParams>,
+) -> ApiReturn<sys_task::Model> {
+    permission.require(permission::NOTIFICATION_ANNOUNCE)?;
+
+    let task = queue::enqueue(
+        &db,
+        &Announce,
+        Announcement {
+            title: params.title,
+            content: params.content,
+            user_ids: params.user_ids,
+        },
+    )
+    .await?;
+
+    Ok(ApiResponse::success(task))
+}
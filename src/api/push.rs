@@ -0,0 +1,119 @@
+use std::{pin::pin, time::Duration};
+
+use axum::{
+    Extension, Router,
+    body::Bytes,
+    extract::ws::{self, CloseFrame, WebSocket, WebSocketUpgrade, close_code},
+    http::HeaderMap,
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing,
+};
+use serde::Deserialize;
+use tokio::time::Instant;
+use tokio_stream::{Stream, StreamExt};
+use tracing::debug;
+
+use crate::{
+    app::{AppState, auth::Principal, extract::Query},
+    push,
+};
+
+/// How often idle connections are pinged, keeping proxies from closing them
+/// and letting clients notice a dead connection.
+const HEARTBEAT: Duration = Duration::from_secs(15);
+
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/events", routing::get(events))
+        .route("/ws", routing::get(websocket))
+}
+
+#[derive(Debug, Deserialize)]
+struct ReconnectQuery {
+    last_event_id: Option<u64>,
+}
+
+/// The id of the last message the client received, from the `Last-Event-ID`
+/// header `EventSource` sends on reconnection, or the `last_event_id` query
+/// parameter for clients that cannot set headers.
+fn last_event_id(headers: &HeaderMap, query: ReconnectQuery) -> Option<u64> {
+    headers
+        .get("last-event-id")
+        .and_then(|id| id.to_str().ok()?.parse().ok())
+        .or(query.last_event_id)
+}
+
+/// Streams the principal's messages as Server-Sent Events, with the message
+/// id as the event id.
+async fn events(
+    Extension(principal): Extension<Principal>,
+    headers: HeaderMap,
+    Query(query): Query<ReconnectQuery>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let messages = push::subscribe(principal.id, last_event_id(&headers, query));
+
+    Sse::new(messages.map(|message| {
+        Event::default()
+            .id(message.id.to_string())
+            .event(&message.event)
+            .json_data(&message.data)
+    }))
+    .keep_alive(KeepAlive::new().interval(HEARTBEAT))
+}
+
+/// Relays the principal's messages over a WebSocket as JSON text frames of
+/// `{ "id", "event", "data" }`.
+async fn websocket(
+    upgrade: WebSocketUpgrade,
+    Extension(principal): Extension<Principal>,
+    headers: HeaderMap,
+    Query(query): Query<ReconnectQuery>,
+) -> impl IntoResponse {
+    let last_event_id = last_event_id(&headers, query);
+
+    upgrade.on_upgrade(move |socket| relay(socket, principal, last_event_id))
+}
+
+async fn relay(mut socket: WebSocket, principal: Principal, last_event_id: Option<u64>) {
+    let mut messages = pin!(push::subscribe(principal.id.clone(), last_event_id));
+    let mut heartbeat = tokio::time::interval_at(Instant::now() + HEARTBEAT, HEARTBEAT);
+
+    loop {
+        tokio::select! {
+            message = messages.next() => {
+                let Some(message) = message else {
+                    // The client fell behind; it reconnects and replays.
+                    let _ = socket
+                        .send(ws::Message::Close(Some(CloseFrame {
+                            code: close_code::AGAIN,
+                            reason: "Reconnect to catch up".into(),
+                        })))
+                        .await;
+                    break;
+                };
+                let text = match serde_json::to_string(&*message) {
+                    Ok(text) => text,
+                    Err(_) => continue,
+                };
+                if socket.send(ws::Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            _ = heartbeat.tick() => {
+                if socket.send(ws::Message::Ping(Bytes::new())).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                Some(Ok(ws::Message::Close(_)) | Err(_)) | None => break,
+                // Pongs are answered by axum; clients have nothing to send.
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    debug!("WebSocket of {} closed", principal.name);
+}
@@ -0,0 +1,253 @@
+use anyhow::Context;
+use axum::{Router, extract::State, routing};
+use sea_orm::{
+    ActiveValue, Condition, DatabaseTransaction, DeriveIntoActiveModel, Order, PaginatorTrait,
+    QueryOrder, TransactionTrait, prelude::*, sea_query,
+};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    app::{
+        ApiReturn, AppState,
+        crud::{ApplyUpdate, CrudHooks, CrudRouter, Operation, update_params},
+        error::{ApiError, ApiResult},
+        extract::{Path, ValidJson},
+        params::{Filter, Sortable},
+        permission,
+        response::ApiResponse,
+        scope::DataPermission,
+    },
+    entity::{
+        data_scope::DataScope,
+        prelude::*,
+        sys_menu,
+        sys_role::{self, ActiveModel},
+        sys_role_menu, sys_user_role,
+    },
+};
+
+pub fn create_router() -> Router<AppState> {
+    CrudRouter::<SysRole, RoleParams, UpdateRoleParams>::new()
+        .filter::<RoleFilter>()
+        .hooks(RoleHooks)
+        .authorize(|permission, operation| {
+            matches!(operation, Operation::List | Operation::Get)
+                || permission.has(permission::ROLE_EDIT)
+        })
+        .build()
+        .route(
+            "/{id}/menus",
+            routing::get(get_role_menus).put(set_role_menus),
+        )
+}
+
+async fn find_role(db: &DatabaseConnection, role_id: String) -> ApiResult<sys_role::Model> {
+    SysRole::find_by_id(role_id)
+        .one(db)
+        .await
+        .context("Find role by id")?
+        .ok_or(ApiError::NotFound)
+}
+
+async fn get_role_menus(
+    State(AppState { db, .. }): State<AppState>,
+    Path(role_id): Path<String>,
+) -> ApiReturn<Vec<sys_menu::Model>> {
+    let role = find_role(&db, role_id).await?;
+    let menus = SysMenu::find()
+        .filter(
+            sys_menu::Column::Id.in_subquery(
+                sea_query::Query::select()
+                    .column(sys_role_menu::Column::MenuId)
+                    .from(SysRoleMenu)
+                    .and_where(sys_role_menu::Column::RoleId.eq(role.id))
+                    .to_owned(),
+            ),
+        )
+        .order_by_asc(sys_menu::Column::Sort)
+        .order_by_asc(sys_menu::Column::Name)
+        .all(&db)
+        .await
+        .context("Failed to fetch role menus")?;
+
+    Ok(ApiResponse::success(menus))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct RoleMenusParams {
+    menu_ids: Vec<String>,
+}
+
+/// Replaces the menus granted by a role.
+async fn set_role_menus(
+    State(AppState { db, .. }): State<AppState>,
+    Path(role_id): Path<String>,
+    permission: DataPermission,
+    ValidJson(params): ValidJson<RoleMenusParams>,
+) -> ApiReturn<()> {
+    permission.require(permission::ROLE_EDIT)?;
+    let role = find_role(&db, role_id).await?;
+    let mut menu_ids = params.menu_ids;
+    menu_ids.sort();
+    menu_ids.dedup();
+
+    let known = SysMenu::find()
+        .filter(sys_menu::Column::Id.is_in(menu_ids.clone()))
+        .count(&db)
+        .await
+        .context("Count menus by menu_id")?;
+    if known != menu_ids.len() as u64 {
+        return Err(ApiError::ValidationError(
+            "menu_ids: Menu not found".to_string(),
+        ));
+    }
+
+    let txn = db.begin().await.context("Begin transaction")?;
+    SysRoleMenu::delete_many()
+        .filter(sys_role_menu::Column::RoleId.eq(&role.id))
+        .exec(&txn)
+        .await
+        .context("Clear role menus")?;
+    if !menu_ids.is_empty() {
+        SysRoleMenu::insert_many(
+            menu_ids
+                .into_iter()
+                .map(|menu_id| sys_role_menu::ActiveModel {
+                    role_id: ActiveValue::Set(role.id.clone()),
+                    menu_id: ActiveValue::Set(menu_id),
+                }),
+        )
+        .exec(&txn)
+        .await
+        .context("Grant role menus")?;
+    }
+    txn.commit().await.context("Commit transaction")?;
+
+    Ok(ApiResponse::success(()))
+}
+
+impl Sortable for SysRole {
+    fn sort_column(field: &str) -> Option<sys_role::Column> {
+        match field {
+            "code" => Some(sys_role::Column::Code),
+            "name" => Some(sys_role::Column::Name),
+            "dataScope" => Some(sys_role::Column::DataScope),
+            "createdAt" => Some(sys_role::Column::CreatedAt),
+            _ => None,
+        }
+    }
+
+    fn default_sort() -> Vec<(sys_role::Column, Order)> {
+        vec![(sys_role::Column::Code, Order::Asc)]
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct RoleFilter {
+    keyword: Option<String>,
+    data_scope: Option<DataScope>,
+}
+
+impl Filter for RoleFilter {
+    type Entity = SysRole;
+
+    fn condition(&self) -> Condition {
+        Condition::all()
+            .add_option(self.keyword.as_ref().map(|keyword| {
+                Condition::any()
+                    .add(sys_role::Column::Code.contains(keyword))
+                    .add(sys_role::Column::Name.contains(keyword))
+            }))
+            .add_option(
+                self.data_scope
+                    .map(|data_scope| sys_role::Column::DataScope.eq(data_scope)),
+            )
+    }
+}
+
+#[derive(Debug, Deserialize, Validate, DeriveIntoActiveModel)]
+struct RoleParams {
+    #[validate(length(
+        min = 1,
+        max = 32,
+        message = "Code must be between 1 and 32 characters long"
+    ))]
+    code: String,
+    #[validate(length(
+        min = 1,
+        max = 32,
+        message = "Name must be between 1 and 32 characters long"
+    ))]
+    name: String,
+    data_scope: DataScope,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct UpdateRoleParams {
+    #[validate(length(
+        min = 1,
+        max = 32,
+        message = "Name must be between 1 and 32 characters long"
+    ))]
+    name: Option<String>,
+    data_scope: Option<DataScope>,
+}
+
+impl ApplyUpdate<ActiveModel> for UpdateRoleParams {
+    fn apply_to(self, active_model: &mut ActiveModel) {
+        update_params!(active_model, name, self.name);
+        update_params!(active_model, data_scope, self.data_scope);
+    }
+}
+
+struct RoleHooks;
+
+#[async_trait::async_trait]
+impl CrudHooks<SysRole> for RoleHooks {
+    async fn before_save(
+        &self,
+        txn: &DatabaseTransaction,
+        active_model: &mut ActiveModel,
+        insert: bool,
+    ) -> ApiResult<()> {
+        let ActiveValue::Set(code) = &active_model.code else {
+            return Ok(());
+        };
+        if !insert {
+            return Ok(());
+        }
+
+        let exists = SysRole::find()
+            .filter(sys_role::Column::Code.eq(code))
+            .one(txn)
+            .await
+            .context("Find role by code")?
+            .is_some();
+        if exists {
+            return Err(ApiError::Conflict(format!(
+                "Role code `{code}` is already taken"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Unassigns the role from its users and revokes its menus.
+    async fn before_delete(
+        &self,
+        txn: &DatabaseTransaction,
+        role: &sys_role::Model,
+    ) -> ApiResult<()> {
+        SysUserRole::delete_many()
+            .filter(sys_user_role::Column::RoleId.eq(&role.id))
+            .exec(txn)
+            .await
+            .context("Unassign the role")?;
+        SysRoleMenu::delete_many()
+            .filter(sys_role_menu::Column::RoleId.eq(&role.id))
+            .exec(txn)
+            .await
+            .context("Revoke the role's menus")?;
+        Ok(())
+    }
+}
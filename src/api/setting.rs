@@ -0,0 +1,124 @@
+use anyhow::Context;
+use axum::Router;
+use sea_orm::{
+    ActiveValue, Condition, DatabaseTransaction, DeriveIntoActiveModel, JsonValue, Order,
+    prelude::*,
+};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    app::{
+        AppState,
+        crud::{ApplyUpdate, CrudHooks, CrudRouter, update_params},
+        error::{ApiError, ApiResult},
+        params::{Filter, Sortable},
+        permission,
+    },
+    entity::{
+        prelude::*,
+        sys_config::{self, ActiveModel},
+    },
+    setting,
+};
+
+pub fn create_router() -> Router<AppState> {
+    CrudRouter::<SysConfig, SettingParams, UpdateSettingParams>::new()
+        .filter::<SettingFilter>()
+        .hooks(SettingHooks)
+        .authorize(|permission, _| permission.has(permission::SETTING_MANAGE))
+        .build()
+}
+
+impl Sortable for SysConfig {
+    fn sort_column(field: &str) -> Option<sys_config::Column> {
+        match field {
+            "key" => Some(sys_config::Column::Key),
+            "updatedAt" => Some(sys_config::Column::UpdatedAt),
+            _ => None,
+        }
+    }
+
+    fn default_sort() -> Vec<(sys_config::Column, Order)> {
+        vec![(sys_config::Column::Key, Order::Asc)]
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct SettingFilter {
+    keyword: Option<String>,
+}
+
+impl Filter for SettingFilter {
+    type Entity = SysConfig;
+
+    fn condition(&self) -> Condition {
+        Condition::all().add_option(
+            self.keyword
+                .as_ref()
+                .map(|keyword| sys_config::Column::Key.contains(keyword)),
+        )
+    }
+}
+
+/// `key` is the path of the entry in `application.toml`, e.g.
+/// `auth.expiration`, and `value` is its JSON value.
+#[derive(Debug, Deserialize, Validate, DeriveIntoActiveModel)]
+struct SettingParams {
+    key: String,
+    value: JsonValue,
+    #[validate(length(max = 255, message = "Remark must be at most 255 characters long"))]
+    remark: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct UpdateSettingParams {
+    value: Option<JsonValue>,
+    #[validate(length(max = 255, message = "Remark must be at most 255 characters long"))]
+    remark: Option<String>,
+}
+
+impl ApplyUpdate<ActiveModel> for UpdateSettingParams {
+    fn apply_to(self, active_model: &mut ActiveModel) {
+        update_params!(active_model, value, self.value);
+        update_params!(active_model, remark, self.remark.map(Some));
+    }
+}
+
+struct SettingHooks;
+
+#[async_trait::async_trait]
+impl CrudHooks<SysConfig> for SettingHooks {
+    async fn before_save(
+        &self,
+        txn: &DatabaseTransaction,
+        active_model: &mut ActiveModel,
+        insert: bool,
+    ) -> ApiResult<()> {
+        let (ActiveValue::Set(key) | ActiveValue::Unchanged(key)) = &active_model.key else {
+            return Ok(());
+        };
+        if let ActiveValue::Set(value) = &active_model.value {
+            setting::check(key, value).map_err(ApiError::ValidationError)?;
+        }
+        if !insert {
+            return Ok(());
+        }
+
+        let exists = SysConfig::find_by_id(key)
+            .one(txn)
+            .await
+            .context("Find setting by key")?
+            .is_some();
+        if exists {
+            return Err(ApiError::Conflict(format!(
+                "Setting `{key}` is already stored"
+            )));
+        }
+        Ok(())
+    }
+
+    async fn after_commit(&self, db: &DatabaseConnection) -> ApiResult<()> {
+        Ok(setting::reload(db).await?)
+    }
+}
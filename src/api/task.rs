@@ -0,0 +1,116 @@
+use anyhow::Context;
+use axum::{Router, extract::State, routing};
+use sea_orm::{Condition, Order, prelude::*};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    app::{
+        ApiReturn, AppState,
+        error::{ApiError, ApiResult},
+        extract::{Path, ValidQuery},
+        params::{Filter, Paged, QuerySpec, Sortable},
+        permission,
+        response::ApiResponse,
+        scope::DataPermission,
+    },
+    entity::{prelude::*, sys_task, task_status::TaskStatus},
+    queue,
+};
+
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/", routing::get(get_tasks))
+        .route("/{id}", routing::get(get_task))
+        .route("/{id}/retry", routing::put(retry_task))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct TaskFilter {
+    kind: Option<String>,
+    status: Option<TaskStatus>,
+}
+
+impl Filter for TaskFilter {
+    type Entity = SysTask;
+
+    fn condition(&self) -> Condition {
+        Condition::all()
+            .add_option(
+                self.kind
+                    .as_ref()
+                    .map(|kind| sys_task::Column::Kind.eq(kind)),
+            )
+            .add_option(
+                self.status
+                    .map(|status| sys_task::Column::Status.eq(status)),
+            )
+    }
+}
+
+impl Sortable for SysTask {
+    fn sort_column(field: &str) -> Option<sys_task::Column> {
+        match field {
+            "kind" => Some(sys_task::Column::Kind),
+            "attempts" => Some(sys_task::Column::Attempts),
+            "runAt" => Some(sys_task::Column::RunAt),
+            "createdAt" => Some(sys_task::Column::CreatedAt),
+            "updatedAt" => Some(sys_task::Column::UpdatedAt),
+            _ => None,
+        }
+    }
+
+    fn default_sort() -> Vec<(sys_task::Column, Order)> {
+        vec![(sys_task::Column::CreatedAt, Order::Desc)]
+    }
+}
+
+async fn find_task(db: &DatabaseConnection, task_id: &str) -> ApiResult<sys_task::Model> {
+    SysTask::find_by_id(task_id)
+        .one(db)
+        .await
+        .context("Find task by id")?
+        .ok_or(ApiError::NotFound)
+}
+
+async fn get_tasks(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    ValidQuery(query): ValidQuery<QuerySpec<TaskFilter>>,
+) -> ApiReturn<Paged<sys_task::Model>> {
+    permission.require(permission::TASK_MANAGE)?;
+    let (select, pagination) = query.into_select();
+
+    let tasks = pagination
+        .fetch(select, sys_task::Column::Id, &db)
+        .await
+        .context("Failed to fetch tasks")?;
+
+    Ok(ApiResponse::success(tasks))
+}
+
+async fn get_task(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    Path(task_id): Path<String>,
+) -> ApiReturn<sys_task::Model> {
+    permission.require(permission::TASK_MANAGE)?;
+
+    Ok(ApiResponse::success(find_task(&db, &task_id).await?))
+}
+
+/// Runs a dead or waiting task again now, with its attempts reset.
+async fn retry_task(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    Path(task_id): Path<String>,
+) -> ApiReturn<sys_task::Model> {
+    permission.require(permission::TASK_MANAGE)?;
+    let task = find_task(&db, &task_id).await?;
+
+    let task = queue::retry(&db, &task).await?.ok_or_else(|| {
+        ApiError::Conflict("Only dead or pending tasks can be retried".to_string())
+    })?;
+
+    Ok(ApiResponse::success(task))
+}
@@ -1,36 +1,182 @@
 use crate::{
     app::{
-        ApiReturn,
-        extract::{Path, ValidJson, ValidQuery},
-        params::{Page, QueryParams},
+        ApiReturn, TaggedReturn,
+        auth::Principal,
+        crud::update_params,
+        error::{ApiError, ApiResult},
+        etag::{Preconditions, Versioned},
+        extract::{Path, Query, ValidJson, ValidMergePatch, ValidQuery},
+        history,
+        params::{Filter, Paged, Pagination, QuerySpec, Sortable},
+        patch::{ApplyPatch, Patch},
+        permission,
+        scope::DataPermission,
+        upload,
         util::hash_password_fast,
     },
     entity::{
         gender::Gender,
         prelude::*,
+        sys_dept, sys_role,
         sys_user::{self, ActiveModel},
+        sys_user_history, sys_user_role,
     },
+    notify::{self, Notice},
 };
 use anyhow::Context;
-use axum::{Router, extract::State, routing};
-use sea_orm::{ActiveValue, Condition, IntoActiveModel, QueryOrder, QueryTrait, prelude::*};
+use axum::{
+    Extension, Router,
+    extract::{DefaultBodyLimit, Multipart, State},
+    http::header,
+    response::IntoResponse,
+    routing,
+};
+use image::ImageFormat;
+use sea_orm::{
+    ActiveValue, Condition, IntoActiveModel, Order, PaginatorTrait, QueryOrder, QueryTrait,
+    TransactionTrait, prelude::*, sea_query,
+};
 use serde::Deserialize;
+use serde_with::{DisplayFromStr, serde_as};
 use validator::Validate;
 
-use crate::app::{AppState, response::ApiResponse};
+use crate::{
+    app::{
+        AppState,
+        response::{ApiResponse, Tagged},
+    },
+    config,
+    event::{UserCreated, UserDeleted, UserDisabled, UserUpdated},
+    search,
+};
+
+const AVATAR_FORMATS: &[ImageFormat] = &[
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+];
+
+const AVATAR_THUMBNAIL_SIZE: u32 = 128;
 
 pub fn create_router() -> Router<AppState> {
     Router::new()
         .route("/", routing::get(get_users).post(create_user))
-        .route("/{id}", routing::put(update_user).delete(delete_user))
+        .route(
+            "/{id}",
+            routing::get(get_user)
+                .put(update_user)
+                .patch(patch_user)
+                .delete(delete_user),
+        )
+        .route("/{id}/history", routing::get(get_user_history))
+        .route(
+            "/{id}/roles",
+            routing::get(get_user_roles).put(set_user_roles),
+        )
+        .route(
+            "/{id}/avatar",
+            routing::get(get_avatar)
+                .post(upload_avatar)
+                // The upload is bounded by `max_avatar_size` while it is read.
+                .layer(DefaultBodyLimit::disable()),
+        )
 }
 
+#[serde_as]
 #[derive(Debug, Deserialize, Validate)]
-struct UserQueryParams {
+struct UserFilter {
     keyword: Option<String>,
-    #[validate(nested)]
-    #[serde(flatten)]
-    pagination: QueryParams,
+    gender: Option<Gender>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    enabled: Option<bool>,
+    birthday_from: Option<Date>,
+    birthday_to: Option<Date>,
+    created_at_from: Option<DateTime>,
+    created_at_to: Option<DateTime>,
+    dept_id: Option<String>,
+    /// Also matches users of the descendants of `dept_id`.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    include_children: Option<bool>,
+}
+
+impl Filter for UserFilter {
+    type Entity = SysUser;
+
+    fn condition(&self) -> Condition {
+        Condition::all()
+            .add_option(
+                self.gender
+                    .map(|gender| sys_user::Column::Gender.eq(gender)),
+            )
+            .add_option(
+                self.enabled
+                    .map(|enabled| sys_user::Column::Enabled.eq(enabled)),
+            )
+            .add_option(
+                self.birthday_from
+                    .map(|from| sys_user::Column::Birthday.gte(from)),
+            )
+            .add_option(
+                self.birthday_to
+                    .map(|to| sys_user::Column::Birthday.lte(to)),
+            )
+            .add_option(
+                self.created_at_from
+                    .map(|from| sys_user::Column::CreatedAt.gte(from)),
+            )
+            .add_option(
+                self.created_at_to
+                    .map(|to| sys_user::Column::CreatedAt.lte(to)),
+            )
+            .add_option(self.dept_id.as_ref().map(|dept_id| {
+                if self.include_children.unwrap_or(false) {
+                    sys_user::Column::DeptId.in_subquery(
+                        sea_query::Query::select()
+                            .column(sys_dept::Column::Id)
+                            .from(SysDept)
+                            .and_where(sys_dept::subtree(dept_id))
+                            .to_owned(),
+                    )
+                } else {
+                    sys_user::Column::DeptId.eq(dept_id)
+                }
+            }))
+    }
+
+    fn search(&self, select: Select<SysUser>, ranked: bool) -> Select<SysUser> {
+        match &self.keyword {
+            Some(keyword) => search::SYS_USER.apply(select, keyword, ranked),
+            None => select,
+        }
+    }
+}
+
+impl Sortable for SysUser {
+    fn sort_column(field: &str) -> Option<sys_user::Column> {
+        match field {
+            "name" => Some(sys_user::Column::Name),
+            "account" => Some(sys_user::Column::Account),
+            "gender" => Some(sys_user::Column::Gender),
+            "birthday" => Some(sys_user::Column::Birthday),
+            "enabled" => Some(sys_user::Column::Enabled),
+            "createdAt" => Some(sys_user::Column::CreatedAt),
+            "updatedAt" => Some(sys_user::Column::UpdatedAt),
+            _ => None,
+        }
+    }
+
+    fn default_sort() -> Vec<(sys_user::Column, Order)> {
+        vec![(sys_user::Column::CreatedAt, Order::Desc)]
+    }
+}
+
+impl Versioned for sys_user::Model {
+    fn version(&self) -> String {
+        format!("{:x}", self.updated_at.and_utc().timestamp_micros())
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Validate, DeriveIntoActiveModel)]
@@ -41,6 +187,7 @@ struct UserParams {
         message = "Name must be between 1 and 16 characters long"
     ))]
     pub name: String,
+    #[validate(custom(function = "crate::app::validation::validate_gender"))]
     pub gender: Gender,
     #[validate(length(
         min = 1,
@@ -59,6 +206,7 @@ struct UserParams {
     pub birthday: Date,
     #[serde(default)]
     pub enabled: bool,
+    pub dept_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Validate)]
@@ -69,6 +217,7 @@ struct UpdateUserParams {
         message = "Name must be between 1 and 16 characters long"
     ))]
     pub name: Option<String>,
+    #[validate(custom(function = "crate::app::validation::validate_gender"))]
     pub gender: Option<Gender>,
     #[validate(length(
         min = 1,
@@ -86,41 +235,146 @@ struct UpdateUserParams {
     pub mobile_phone: Option<String>,
     pub birthday: Option<Date>,
     pub enabled: Option<bool>,
+    pub dept_id: Option<String>,
+}
+
+/// A JSON Merge Patch of a user. Apart from `dept_id`, none of the user's
+/// columns are nullable, so an explicit `null` is rejected for them.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+#[serde(default)]
+struct PatchUserParams {
+    #[validate(
+        required(message = "Name cannot be null"),
+        length(
+            min = 1,
+            max = 16,
+            message = "Name must be between 1 and 16 characters long"
+        )
+    )]
+    pub name: Patch<String>,
+    #[validate(
+        required(message = "Gender cannot be null"),
+        custom(function = "crate::app::validation::validate_gender_patch")
+    )]
+    pub gender: Patch<Gender>,
+    #[validate(
+        required(message = "Account cannot be null"),
+        length(
+            min = 1,
+            max = 16,
+            message = "Account must be between 1 and 16 characters long"
+        )
+    )]
+    pub account: Patch<String>,
+    #[validate(
+        required(message = "Password cannot be null"),
+        length(
+            min = 6,
+            max = 16,
+            message = "Password must be between 6 and 16 characters long"
+        )
+    )]
+    pub password: Patch<String>,
+    #[validate(
+        required(message = "Mobile phone cannot be null"),
+        custom(function = "crate::app::validation::validate_mobile_phone_patch")
+    )]
+    pub mobile_phone: Patch<String>,
+    #[validate(required(message = "Birthday cannot be null"))]
+    pub birthday: Patch<Date>,
+    #[validate(required(message = "Enabled cannot be null"))]
+    pub enabled: Patch<bool>,
+    pub dept_id: Patch<String>,
+}
+
+async fn get_user(
+    State(AppState { db, .. }): State<AppState>,
+    Path(user_id): Path<String>,
+    permission: DataPermission,
+    preconditions: Preconditions,
+) -> TaggedReturn<sys_user::Model> {
+    let user = find_user(&db, &user_id, &permission).await?;
+
+    Ok(Tagged::conditional(user, &preconditions))
+}
+
+/// Finds a user within the principal's data scope. Users outside of it are
+/// reported as missing rather than forbidden.
+async fn find_user(
+    db: &DatabaseConnection,
+    user_id: &str,
+    permission: &DataPermission,
+) -> ApiResult<sys_user::Model> {
+    SysUser::find_by_id(user_id)
+        .filter(permission.user_condition())
+        .one(db)
+        .await
+        .context("Find user by user_id")?
+        .ok_or(ApiError::NotFound)
 }
 
 async fn create_user(
-    State(AppState { db }): State<AppState>,
+    State(state): State<AppState>,
+    permission: DataPermission,
     ValidJson(user_params): ValidJson<UserParams>,
-) -> ApiReturn<sys_user::Model> {
+) -> TaggedReturn<sys_user::Model> {
+    let db = &state.db;
+    check_dept(db, &permission, None, user_params.dept_id.as_deref()).await?;
     let mut active_model = user_params.into_active_model();
 
     active_model.password = ActiveValue::set(hash_password_fast(active_model.password.as_ref())?);
 
-    Ok(ApiResponse::success(
-        active_model.insert(&db).await.context("Create user")?,
-    ))
+    let txn = db.begin().await.context("Begin transaction")?;
+    let user = active_model.insert(&txn).await.context("Create user")?;
+    let mut events = state.events.batch();
+    events.publish(&txn, UserCreated(user.clone())).await?;
+    txn.commit().await.context("Commit transaction")?;
+    events.deliver(&state).await;
+
+    Ok(Tagged::fresh(user))
 }
 
-macro_rules! update_params {
-    ($active_model:expr, $field:ident, $value:expr) => {
-        if let Some(value) = $value {
-            $active_model.$field = ActiveValue::Set(value);
-        }
+/// Rejects a `dept_id` that names no department within the principal's data
+/// scope, so that users cannot be moved, the principal included, to where
+/// the principal would see more. Keeping the `current` department is always
+/// allowed.
+async fn check_dept(
+    db: &DatabaseConnection,
+    permission: &DataPermission,
+    current: Option<&str>,
+    dept_id: Option<&str>,
+) -> ApiResult<()> {
+    let Some(dept_id) = dept_id.filter(|&dept_id| Some(dept_id) != current) else {
+        return Ok(());
     };
+    let exists = SysDept::find_by_id(dept_id)
+        .filter(permission.dept_condition())
+        .count(db)
+        .await
+        .context("Find department by dept_id")?
+        > 0;
+    if !exists {
+        return Err(ApiError::ValidationError(
+            "dept_id: Department not found".to_string(),
+        ));
+    }
+    Ok(())
 }
 
 async fn update_user(
-    State(AppState { db }): State<AppState>,
+    State(state): State<AppState>,
     Path(user_id): Path<String>,
+    permission: DataPermission,
+    Extension(principal): Extension<Principal>,
+    preconditions: Preconditions,
     ValidJson(user_params): ValidJson<UpdateUserParams>,
-) -> ApiReturn<sys_user::Model> {
-    let user = SysUser::find_by_id(user_id)
-        .one(&db)
-        .await
-        .context("Find User")?
-        .ok_or_else(|| anyhow::anyhow!("User not found"))?;
+) -> TaggedReturn<sys_user::Model> {
+    let db = &state.db;
+    let user = find_user(db, &user_id, &permission).await?;
+
+    preconditions.check_write(&user.etag())?;
 
-    let mut active_model = user.into_active_model();
+    let mut active_model = user.clone().into_active_model();
 
     update_params!(active_model, name, user_params.name);
     update_params!(active_model, gender, user_params.gender);
@@ -128,27 +382,271 @@ async fn update_user(
     update_params!(active_model, mobile_phone, user_params.mobile_phone);
     update_params!(active_model, birthday, user_params.birthday);
     update_params!(active_model, enabled, user_params.enabled);
+    if let Some(dept_id) = user_params.dept_id {
+        check_dept(db, &permission, user.dept_id.as_deref(), Some(&dept_id)).await?;
+        active_model.dept_id = ActiveValue::Set(Some(dept_id));
+    }
 
     if let Some(password) = user_params.password {
         active_model.password = ActiveValue::Set(hash_password_fast(&password)?);
     }
 
-    Ok(ApiResponse::success(
-        active_model.update(&db).await.context("Update user")?,
+    Ok(Tagged::fresh(
+        save_user(&state, user, active_model, &preconditions, &principal).await?,
     ))
 }
 
-async fn delete_user(
-    State(AppState { db }): State<AppState>,
+macro_rules! patch_params {
+    ($active_model:expr, $field:ident, $value:expr) => {
+        $value.apply(stringify!($field), &mut $active_model.$field)?;
+    };
+}
+
+async fn patch_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+    permission: DataPermission,
+    Extension(principal): Extension<Principal>,
+    preconditions: Preconditions,
+    ValidMergePatch(user_params): ValidMergePatch<PatchUserParams>,
+) -> TaggedReturn<sys_user::Model> {
+    let db = &state.db;
+    let user = find_user(db, &user_id, &permission).await?;
+
+    preconditions.check_write(&user.etag())?;
+
+    let mut active_model = user.clone().into_active_model();
+
+    patch_params!(active_model, name, user_params.name);
+    patch_params!(active_model, gender, user_params.gender);
+    patch_params!(active_model, account, user_params.account);
+    patch_params!(active_model, mobile_phone, user_params.mobile_phone);
+    patch_params!(active_model, birthday, user_params.birthday);
+    patch_params!(active_model, enabled, user_params.enabled);
+    check_dept(
+        db,
+        &permission,
+        user.dept_id.as_deref(),
+        user_params.dept_id.as_value().map(String::as_str),
+    )
+    .await?;
+    patch_params!(active_model, dept_id, user_params.dept_id);
+
+    if let Some(password) = user_params.password.as_value() {
+        active_model.password = ActiveValue::Set(hash_password_fast(password)?);
+    }
+
+    Ok(Tagged::fresh(
+        save_user(&state, user, active_model, &preconditions, &principal).await?,
+    ))
+}
+
+/// Saves an update of `before`, recording its change history and publishing
+/// its events in the same transaction.
+async fn save_user(
+    state: &AppState,
+    before: sys_user::Model,
+    active_model: ActiveModel,
+    preconditions: &Preconditions,
+    principal: &Principal,
+) -> ApiResult<sys_user::Model> {
+    let txn = state.db.begin().await.context("Begin transaction")?;
+    let mut events = state.events.batch();
+
+    let user = if preconditions.is_conditional() {
+        // Re-check the version in the UPDATE itself so that a concurrent
+        // write between the read above and this statement is still caught.
+        let active_model = active_model
+            .before_save(&txn, false)
+            .await
+            .context("Update user")?;
+        SysUser::update(active_model)
+            .filter(sys_user::Column::UpdatedAt.eq(before.updated_at))
+            .exec(&txn)
+            .await
+            .map_err(|e| match e {
+                DbErr::RecordNotUpdated => ApiError::PreconditionFailed,
+                e => anyhow::Error::new(e).context("Update user").into(),
+            })?
+    } else {
+        active_model.update(&txn).await.context("Update user")?
+    };
+
+    // Pushed once the transaction commits.
+    let push = if user.password != before.password {
+        let notice = Notice::security(
+            "Password changed",
+            format!(
+                "The password of your account was changed by {}. Contact an administrator if it was not you.",
+                principal.name
+            ),
+        );
+        Some(notify::notify(&txn, &user.id, notice).await?)
+    } else {
+        None
+    };
+
+    if let Some((old, new)) =
+        history::diff(&before, &user, &["updatedAt"]).context("Diff user changes")?
+    {
+        sys_user_history::ActiveModel {
+            user_id: ActiveValue::Set(user.id.clone()),
+            before: ActiveValue::Set(old),
+            after: ActiveValue::Set(new),
+            operator_id: ActiveValue::Set(principal.id.clone()),
+            operator_name: ActiveValue::Set(principal.name.clone()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .context("Record user history")?;
+
+        events.publish(&txn, UserUpdated(user.clone())).await?;
+        if before.enabled && !user.enabled {
+            events.publish(&txn, UserDisabled(user.clone())).await?;
+        }
+    }
+
+    txn.commit().await.context("Commit transaction")?;
+    events.deliver(state).await;
+    if let Some(push) = push {
+        push.send();
+    }
+
+    Ok(user)
+}
+
+async fn get_user_history(
+    State(AppState { db, .. }): State<AppState>,
+    Path(user_id): Path<String>,
+    permission: DataPermission,
+    ValidQuery(pagination): ValidQuery<Pagination>,
+) -> ApiReturn<Paged<sys_user_history::Model>> {
+    let user = find_user(&db, &user_id, &permission).await?;
+    let select = SysUserHistory::find()
+        .filter(sys_user_history::Column::UserId.eq(user.id))
+        .order_by_desc(sys_user_history::Column::Id);
+
+    let history = pagination
+        .fetch(select, sys_user_history::Column::Id, &db)
+        .await
+        .context("Failed to fetch user history")?;
+
+    Ok(ApiResponse::success(history))
+}
+
+async fn get_user_roles(
+    State(AppState { db, .. }): State<AppState>,
     Path(user_id): Path<String>,
+    permission: DataPermission,
+) -> ApiReturn<Vec<sys_role::Model>> {
+    let user = find_user(&db, &user_id, &permission).await?;
+    let roles = SysRole::find()
+        .filter(
+            sys_role::Column::Id.in_subquery(
+                sea_query::Query::select()
+                    .column(sys_user_role::Column::RoleId)
+                    .from(SysUserRole)
+                    .and_where(sys_user_role::Column::UserId.eq(user.id))
+                    .to_owned(),
+            ),
+        )
+        .order_by_asc(sys_role::Column::Code)
+        .all(&db)
+        .await
+        .context("Failed to fetch user roles")?;
+
+    Ok(ApiResponse::success(roles))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct UserRolesParams {
+    role_ids: Vec<String>,
+}
+
+/// Replaces the roles of a user.
+async fn set_user_roles(
+    State(AppState { db, .. }): State<AppState>,
+    Path(user_id): Path<String>,
+    permission: DataPermission,
+    ValidJson(params): ValidJson<UserRolesParams>,
 ) -> ApiReturn<()> {
-    let user = SysUser::find_by_id(&user_id)
-        .one(&db)
+    permission.require(permission::USER_ROLES)?;
+    let user = find_user(&db, &user_id, &permission).await?;
+    let mut role_ids = params.role_ids;
+    role_ids.sort();
+    role_ids.dedup();
+
+    let known = SysRole::find()
+        .filter(sys_role::Column::Id.is_in(role_ids.clone()))
+        .count(&db)
         .await
-        .context("Find user by user_id")?
-        .ok_or(anyhow::anyhow!("User not found"))?;
+        .context("Count roles by role_id")?;
+    if known != role_ids.len() as u64 {
+        return Err(ApiError::ValidationError(
+            "role_ids: Role not found".to_string(),
+        ));
+    }
+
+    let txn = db.begin().await.context("Begin transaction")?;
+    SysUserRole::delete_many()
+        .filter(sys_user_role::Column::UserId.eq(&user.id))
+        .exec(&txn)
+        .await
+        .context("Clear user roles")?;
+    if !role_ids.is_empty() {
+        SysUserRole::insert_many(
+            role_ids
+                .into_iter()
+                .map(|role_id| sys_user_role::ActiveModel {
+                    user_id: ActiveValue::Set(user.id.clone()),
+                    role_id: ActiveValue::Set(role_id),
+                }),
+        )
+        .exec(&txn)
+        .await
+        .context("Assign user roles")?;
+    }
+    txn.commit().await.context("Commit transaction")?;
+
+    Ok(ApiResponse::success(()))
+}
 
-    let result = user.delete(&db).await.context("Delete the user")?;
+async fn delete_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+    permission: DataPermission,
+    preconditions: Preconditions,
+) -> ApiReturn<()> {
+    let db = &state.db;
+    let user = find_user(db, &user_id, &permission).await?;
+
+    preconditions.check_write(&user.etag())?;
+
+    let txn = db.begin().await.context("Begin transaction")?;
+    let mut events = state.events.batch();
+    let result = SysUser::delete_by_id(&user_id)
+        .filter(permission.user_condition())
+        .apply_if(
+            preconditions.is_conditional().then_some(user.updated_at),
+            |query, version| query.filter(sys_user::Column::UpdatedAt.eq(version)),
+        )
+        .exec(&txn)
+        .await
+        .context("Delete the user")?;
+    if preconditions.is_conditional() && result.rows_affected == 0 {
+        return Err(ApiError::PreconditionFailed);
+    }
+    SysUserRole::delete_many()
+        .filter(sys_user_role::Column::UserId.eq(&user_id))
+        .exec(&txn)
+        .await
+        .context("Unassign the user's roles")?;
+    if result.rows_affected > 0 {
+        events.publish(&txn, UserDeleted(user)).await?;
+    }
+    txn.commit().await.context("Commit transaction")?;
+    events.deliver(&state).await;
     tracing::info!(
         "Delete User: {user_id}, rows_affected: {}",
         result.rows_affected
@@ -158,36 +656,110 @@ async fn delete_user(
 }
 
 async fn get_users(
-    State(AppState { db }): State<AppState>,
-    ValidQuery(UserQueryParams {
-        keyword,
-        pagination,
-    }): ValidQuery<UserQueryParams>,
-) -> ApiReturn<Page<sys_user::Model>> {
-    let paginator = SysUser::find()
-        .apply_if(keyword.as_ref(), |query, keyword| {
-            query.filter(
-                Condition::any()
-                    .add(sys_user::Column::Name.contains(keyword))
-                    .add(sys_user::Column::Account.contains(keyword))
-                    .add(sys_user::Column::MobilePhone.contains(keyword))
-                    .add(sys_user::Column::Gender.contains(keyword)),
-            )
-        })
-        .order_by_desc(sys_user::Column::CreatedAt)
-        .paginate(&db, pagination.page_size);
-
-    let size = paginator
-        .num_items()
-        .await
-        .context("Failed to get number of items")?;
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    ValidQuery(query): ValidQuery<QuerySpec<UserFilter>>,
+) -> ApiReturn<Paged<sys_user::Model>> {
+    let (select, pagination) = query.into_select();
+    let select = select.filter(permission.user_condition());
 
-    let users = paginator
-        .fetch_page(pagination.page - 1)
+    let users = pagination
+        .fetch(select, sys_user::Column::Id, &db)
         .await
         .context("Failed to fetch users")?;
 
-    Ok(ApiResponse::success(Page::from_pagination(
-        pagination, size, users,
-    )))
+    Ok(ApiResponse::success(users))
+}
+
+fn avatar_thumbnail_key(key: &str) -> String {
+    let stem = key.rsplit_once('.').map_or(key, |(stem, _)| stem);
+    format!("{stem}.thumb.png")
+}
+
+async fn upload_avatar(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+    permission: DataPermission,
+    Extension(principal): Extension<Principal>,
+    mut multipart: Multipart,
+) -> ApiReturn<sys_user::Model> {
+    let AppState { db, storage, .. } = &state;
+    let user = find_user(db, &user_id, &permission).await?;
+
+    let file = upload::read_file(
+        &mut multipart,
+        "file",
+        config::get().storage().max_avatar_size(),
+    )
+    .await?;
+    let format = upload::sniff_image(&file.data, AVATAR_FORMATS).ok_or(
+        ApiError::UnsupportedMediaType("image/png, image/jpeg, image/gif or image/webp"),
+    )?;
+
+    let data = file.data.clone();
+    let thumbnail = tokio::task::spawn_blocking(move || {
+        upload::thumbnail(&data, format, AVATAR_THUMBNAIL_SIZE)
+    })
+    .await
+    .context("Join thumbnail task")?
+    .map_err(|e| ApiError::ValidationError(format!("file: {e}")))?;
+
+    let extension = format.extensions_str().first().copied().unwrap_or("bin");
+    let key = format!("avatars/{user_id}/{}.{extension}", Uuid::now_v7().simple());
+    storage.put(&key, file.data).await?;
+    storage
+        .put(&avatar_thumbnail_key(&key), thumbnail.into())
+        .await?;
+
+    let previous = user.avatar.clone();
+    let mut active_model = user.clone().into_active_model();
+    active_model.avatar = ActiveValue::Set(Some(key));
+    let user = save_user(
+        &state,
+        user,
+        active_model,
+        &Preconditions::default(),
+        &principal,
+    )
+    .await?;
+
+    if let Some(previous) = previous {
+        for key in [avatar_thumbnail_key(&previous), previous] {
+            if let Err(e) = storage.delete(&key).await {
+                tracing::warn!(error = ?e, key, "Failed to delete the previous avatar");
+            }
+        }
+    }
+
+    Ok(ApiResponse::success(user))
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize)]
+struct AvatarParams {
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(default)]
+    thumbnail: bool,
+}
+
+async fn get_avatar(
+    State(AppState { db, storage, .. }): State<AppState>,
+    Path(user_id): Path<String>,
+    permission: DataPermission,
+    Query(params): Query<AvatarParams>,
+) -> ApiResult<impl IntoResponse> {
+    let key = find_user(&db, &user_id, &permission)
+        .await?
+        .avatar
+        .ok_or(ApiError::NotFound)?;
+    let key = if params.thumbnail {
+        avatar_thumbnail_key(&key)
+    } else {
+        key
+    };
+
+    let data = storage.get(&key).await?.ok_or(ApiError::NotFound)?;
+    let mime = mime_guess::from_path(&key).first_or_octet_stream();
+
+    Ok(([(header::CONTENT_TYPE, mime.to_string())], data))
 }
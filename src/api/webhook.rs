@@ -0,0 +1,295 @@
+use std::borrow::Cow;
+
+use anyhow::Context;
+use axum::{Router, extract::State, routing};
+use reqwest::Url;
+use sea_orm::{
+    ActiveValue, Condition, DatabaseTransaction, IntoActiveModel, JsonValue, Order, QueryOrder,
+    prelude::*,
+};
+use serde::Deserialize;
+use serde_json::json;
+use serde_with::{DisplayFromStr, serde_as};
+use validator::{Validate, ValidationError};
+
+use crate::{
+    app::{
+        ApiReturn, AppState,
+        crud::{ApplyUpdate, CrudHooks, CrudRouter, update_params},
+        error::{ApiError, ApiResult},
+        extract::{Path, ValidQuery},
+        params::{Filter, Paged, Pagination, Sortable},
+        permission,
+        response::ApiResponse,
+        scope::DataPermission,
+    },
+    entity::{
+        prelude::*,
+        sys_webhook::{self, ActiveModel},
+        sys_webhook_delivery,
+    },
+    webhook,
+};
+
+pub fn create_router() -> Router<AppState> {
+    CrudRouter::<SysWebhook, WebhookParams, UpdateWebhookParams>::new()
+        .filter::<WebhookFilter>()
+        .hooks(WebhookHooks)
+        .authorize(|permission, _| permission.has(permission::WEBHOOK_MANAGE))
+        .build()
+        .route("/{id}/ping", routing::post(ping_webhook))
+        .route("/{id}/deliveries", routing::get(get_deliveries))
+        .route(
+            "/{id}/deliveries/{delivery_id}/redeliver",
+            routing::post(redeliver),
+        )
+}
+
+impl Sortable for SysWebhook {
+    fn sort_column(field: &str) -> Option<sys_webhook::Column> {
+        match field {
+            "url" => Some(sys_webhook::Column::Url),
+            "createdAt" => Some(sys_webhook::Column::CreatedAt),
+            "updatedAt" => Some(sys_webhook::Column::UpdatedAt),
+            _ => None,
+        }
+    }
+
+    fn default_sort() -> Vec<(sys_webhook::Column, Order)> {
+        vec![(sys_webhook::Column::CreatedAt, Order::Desc)]
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize, Validate)]
+struct WebhookFilter {
+    keyword: Option<String>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    enabled: Option<bool>,
+}
+
+impl Filter for WebhookFilter {
+    type Entity = SysWebhook;
+
+    fn condition(&self) -> Condition {
+        Condition::all()
+            .add_option(
+                self.keyword
+                    .as_ref()
+                    .map(|keyword| sys_webhook::Column::Url.contains(keyword)),
+            )
+            .add_option(
+                self.enabled
+                    .map(|enabled| sys_webhook::Column::Enabled.eq(enabled)),
+            )
+    }
+}
+
+fn validate_url(url: &str) -> Result<(), ValidationError> {
+    match Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(()),
+        _ => Err(ValidationError::new("url")
+            .with_message(Cow::Borrowed("Url must be an http or https URL"))),
+    }
+}
+
+fn validate_events(events: &[String]) -> Result<(), ValidationError> {
+    if events.is_empty() {
+        return Err(ValidationError::new("events")
+            .with_message(Cow::Borrowed("Subscribe to at least one event")));
+    }
+    match events.iter().find(|event| {
+        event.as_str() != webhook::ALL_EVENTS && !webhook::EVENTS.contains(&event.as_str())
+    }) {
+        Some(event) => Err(ValidationError::new("events")
+            .with_message(Cow::Owned(format!("Unknown event `{event}`")))),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct WebhookParams {
+    #[validate(
+        length(max = 2048, message = "Url must be at most 2048 characters long"),
+        custom(function = "validate_url")
+    )]
+    url: String,
+    /// Event names from `webhook::EVENTS`, or `*` for all of them.
+    #[validate(custom(function = "validate_events"))]
+    events: Vec<String>,
+    #[validate(length(
+        min = 16,
+        max = 128,
+        message = "Secret must be between 16 and 128 characters long"
+    ))]
+    secret: String,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+    #[validate(length(max = 255, message = "Remark must be at most 255 characters long"))]
+    remark: Option<String>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl IntoActiveModel<ActiveModel> for WebhookParams {
+    fn into_active_model(self) -> ActiveModel {
+        ActiveModel {
+            url: ActiveValue::Set(self.url),
+            events: ActiveValue::Set(json!(self.events)),
+            secret: ActiveValue::Set(self.secret),
+            enabled: ActiveValue::Set(self.enabled),
+            remark: ActiveValue::Set(self.remark),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct UpdateWebhookParams {
+    #[validate(
+        length(max = 2048, message = "Url must be at most 2048 characters long"),
+        custom(function = "validate_url")
+    )]
+    url: Option<String>,
+    #[validate(custom(function = "validate_events"))]
+    events: Option<Vec<String>>,
+    #[validate(length(
+        min = 16,
+        max = 128,
+        message = "Secret must be between 16 and 128 characters long"
+    ))]
+    secret: Option<String>,
+    enabled: Option<bool>,
+    #[validate(length(max = 255, message = "Remark must be at most 255 characters long"))]
+    remark: Option<String>,
+}
+
+impl ApplyUpdate<ActiveModel> for UpdateWebhookParams {
+    fn apply_to(self, active_model: &mut ActiveModel) {
+        update_params!(active_model, url, self.url);
+        update_params!(
+            active_model,
+            events,
+            self.events.map(|events| json!(events))
+        );
+        update_params!(active_model, secret, self.secret);
+        update_params!(active_model, enabled, self.enabled);
+        update_params!(active_model, remark, self.remark.map(Some));
+    }
+}
+
+struct WebhookHooks;
+
+#[async_trait::async_trait]
+impl CrudHooks<SysWebhook> for WebhookHooks {
+    async fn before_save(
+        &self,
+        _txn: &DatabaseTransaction,
+        active_model: &mut ActiveModel,
+        _insert: bool,
+    ) -> ApiResult<()> {
+        if let ActiveValue::Set(url) = &active_model.url {
+            webhook::check_url(url)
+                .await
+                .map_err(|e| ApiError::ValidationError(format!("url: {e:#}")))?;
+        }
+        Ok(())
+    }
+
+    async fn after_delete(
+        &self,
+        txn: &DatabaseTransaction,
+        webhook: &sys_webhook::Model,
+    ) -> ApiResult<()> {
+        // Queued deliveries find their row gone and are dropped.
+        SysWebhookDelivery::delete_many()
+            .filter(sys_webhook_delivery::Column::WebhookId.eq(&webhook.id))
+            .exec(txn)
+            .await
+            .context("Delete the webhook's deliveries")?;
+        Ok(())
+    }
+}
+
+async fn find_webhook(db: &DatabaseConnection, webhook_id: &str) -> ApiResult<sys_webhook::Model> {
+    SysWebhook::find_by_id(webhook_id)
+        .one(db)
+        .await
+        .context("Find webhook by id")?
+        .ok_or(ApiError::NotFound)
+}
+
+/// Queues a `ping` delivery to the webhook, whatever its events, to check
+/// that the receiver accepts and verifies deliveries.
+async fn ping_webhook(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    Path(webhook_id): Path<String>,
+) -> ApiReturn<sys_webhook_delivery::Model> {
+    permission.require(permission::WEBHOOK_MANAGE)?;
+    let webhook = find_webhook(&db, &webhook_id).await?;
+    if !webhook.enabled {
+        return Err(ApiError::Conflict("The webhook is disabled".to_string()));
+    }
+
+    let delivery = webhook::deliver(
+        &db,
+        &webhook,
+        webhook::PING,
+        json!({ "webhookId": webhook.id }),
+    )
+    .await?;
+
+    Ok(ApiResponse::success(delivery))
+}
+
+/// Lists the deliveries of a webhook, newest first.
+async fn get_deliveries(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    Path(webhook_id): Path<String>,
+    ValidQuery(pagination): ValidQuery<Pagination>,
+) -> ApiReturn<Paged<sys_webhook_delivery::Model>> {
+    permission.require(permission::WEBHOOK_MANAGE)?;
+    let webhook = find_webhook(&db, &webhook_id).await?;
+    let select = SysWebhookDelivery::find()
+        .filter(sys_webhook_delivery::Column::WebhookId.eq(webhook.id))
+        .order_by_desc(sys_webhook_delivery::Column::Id);
+
+    let deliveries = pagination
+        .fetch(select, sys_webhook_delivery::Column::Id, &db)
+        .await
+        .context("Failed to fetch webhook deliveries")?;
+
+    Ok(ApiResponse::success(deliveries))
+}
+
+/// Sends the payload of an earlier delivery again, as a new delivery.
+async fn redeliver(
+    State(AppState { db, .. }): State<AppState>,
+    permission: DataPermission,
+    Path((webhook_id, delivery_id)): Path<(String, String)>,
+) -> ApiReturn<sys_webhook_delivery::Model> {
+    permission.require(permission::WEBHOOK_MANAGE)?;
+    let webhook = find_webhook(&db, &webhook_id).await?;
+    if !webhook.enabled {
+        return Err(ApiError::Conflict("The webhook is disabled".to_string()));
+    }
+    let delivery = SysWebhookDelivery::find_by_id(&delivery_id)
+        .filter(sys_webhook_delivery::Column::WebhookId.eq(&webhook.id))
+        .one(&db)
+        .await
+        .context("Find webhook delivery by id")?
+        .ok_or(ApiError::NotFound)?;
+
+    let data = match delivery.payload {
+        JsonValue::Object(mut payload) => payload.remove("data").unwrap_or_default(),
+        _ => JsonValue::Null,
+    };
+    let delivery = webhook::deliver(&db, &webhook, &delivery.event, data).await?;
+
+    Ok(ApiResponse::success(delivery))
+}
@@ -28,7 +28,6 @@ pub struct JwtService {
     decode_key: DecodingKey,
     header: Header,
     validation: Validation,
-    expiration: u64,
 }
 
 impl JwtService {
@@ -42,7 +41,6 @@ impl JwtService {
             decode_key: DecodingKey::from_secret(config.secret().as_bytes()),
             header: Header::new(config.algorithm()),
             validation,
-            expiration: config.expiration(),
         }
     }
 
@@ -51,7 +49,7 @@ impl JwtService {
 
         let claims = Claims {
             sub: format!("{}:{}", principal.id, principal.name),
-            exp: now.saturating_add(self.expiration),
+            exp: now.saturating_add(config::get().auth().expiration()),
             iat: now,
         };
 
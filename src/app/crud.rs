@@ -0,0 +1,346 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use axum::{Router, extract::State, routing};
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, EntityTrait, IntoActiveModel, Iterable, PrimaryKeyToColumn,
+    PrimaryKeyTrait, TransactionTrait,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tracing::error;
+use validator::{Validate, ValidationErrors};
+
+use crate::app::{
+    ApiReturn, AppState,
+    error::{ApiError, ApiResult},
+    extract::{Path, ValidJson, ValidQuery},
+    params::{Filter, Paged, QuerySpec, Sortable},
+    response::ApiResponse,
+    scope::DataPermission,
+};
+
+/// An operation of a `CrudRouter`, for authorization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    List,
+    Get,
+    Create,
+    Update,
+    Delete,
+}
+
+/// Extension points of a `CrudRouter`, run in the transaction of the database
+/// write. An error from any of them rolls the whole operation back.
+#[async_trait]
+pub trait CrudHooks<E: EntityTrait>: Send + Sync + 'static {
+    /// Runs before a create, with `insert` set, or an update.
+    async fn before_save(
+        &self,
+        _txn: &DatabaseTransaction,
+        _active_model: &mut E::ActiveModel,
+        _insert: bool,
+    ) -> ApiResult<()> {
+        Ok(())
+    }
+
+    async fn after_save(
+        &self,
+        _txn: &DatabaseTransaction,
+        _model: &E::Model,
+        _insert: bool,
+    ) -> ApiResult<()> {
+        Ok(())
+    }
+
+    async fn before_delete(&self, _txn: &DatabaseTransaction, _model: &E::Model) -> ApiResult<()> {
+        Ok(())
+    }
+
+    async fn after_delete(&self, _txn: &DatabaseTransaction, _model: &E::Model) -> ApiResult<()> {
+        Ok(())
+    }
+
+    /// Runs once a create, update or delete committed, to refresh what is
+    /// cached from the table. The write cannot be undone by then, so an error
+    /// is logged and the operation still succeeds.
+    async fn after_commit(&self, _db: &DatabaseConnection) -> ApiResult<()> {
+        Ok(())
+    }
+}
+
+/// Update parameters, applied onto the active model of the current row.
+pub trait ApplyUpdate<A> {
+    fn apply_to(self, active_model: &mut A);
+}
+
+/// Sets `$active_model.$field` when the update parameter `$value` is `Some`.
+macro_rules! update_params {
+    ($active_model:expr, $field:ident, $value:expr) => {
+        if let Some(value) = $value {
+            $active_model.$field = ActiveValue::Set(value);
+        }
+    };
+}
+
+pub(crate) use update_params;
+
+/// The hooks of a `CrudRouter` that sets none.
+pub struct NoHooks;
+
+impl<E: EntityTrait> CrudHooks<E> for NoHooks {}
+
+/// The filter of a `CrudRouter` that sets none, accepting only sorting and
+/// pagination parameters.
+#[derive(Debug, Deserialize)]
+pub struct NoFilter<E> {
+    #[serde(skip)]
+    entity: PhantomData<E>,
+}
+
+impl<E> Validate for NoFilter<E> {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        Ok(())
+    }
+}
+
+impl<E: Sortable> Filter for NoFilter<E> {
+    type Entity = E;
+
+    fn condition(&self) -> sea_orm::Condition {
+        sea_orm::Condition::all()
+    }
+}
+
+type Authorize = dyn Fn(&DataPermission, Operation) -> bool + Send + Sync;
+
+/// Builds list, get, create, update and delete routes for an entity with a
+/// single `String` primary key:
+///
+/// - `GET /` lists rows with the filters `F`, sorting and pagination
+/// - `POST /` creates a row from `C`
+/// - `GET /{id}`, `PUT /{id}` and `DELETE /{id}` address a single row, where
+///   the update applies `U` onto the current row.
+///
+/// The routes load the principal's `DataPermission`, so they must sit behind
+/// `AuthLayer`.
+pub struct CrudRouter<E, C, U, F = NoFilter<E>> {
+    hooks: Arc<dyn CrudHooks<E>>,
+    authorize: Arc<Authorize>,
+    types: PhantomData<fn(C, U) -> F>,
+}
+
+impl<E, C, U> CrudRouter<E, C, U>
+where
+    E: Sortable,
+{
+    pub fn new() -> Self {
+        Self {
+            hooks: Arc::new(NoHooks),
+            authorize: Arc::new(|_, _| true),
+            types: PhantomData,
+        }
+    }
+}
+
+impl<E, C, U, F> CrudRouter<E, C, U, F>
+where
+    E: Sortable,
+    E::Model: Serialize + IntoActiveModel<E::ActiveModel> + Send + Sync,
+    E::ActiveModel: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send + Sync,
+    <E::PrimaryKey as PrimaryKeyTrait>::ValueType: From<String>,
+    C: DeserializeOwned + Validate + IntoActiveModel<E::ActiveModel> + Send + 'static,
+    U: DeserializeOwned + Validate + ApplyUpdate<E::ActiveModel> + Send + 'static,
+    F: Filter<Entity = E> + DeserializeOwned + Validate + Send + 'static,
+{
+    /// Lists with the filters `F2` instead.
+    pub fn filter<F2>(self) -> CrudRouter<E, C, U, F2> {
+        CrudRouter {
+            hooks: self.hooks,
+            authorize: self.authorize,
+            types: PhantomData,
+        }
+    }
+
+    pub fn hooks(mut self, hooks: impl CrudHooks<E>) -> Self {
+        self.hooks = Arc::new(hooks);
+        self
+    }
+
+    /// Rejects operations with `403 Forbidden` unless `authorize` allows
+    /// them for the principal.
+    pub fn authorize(
+        mut self,
+        authorize: impl Fn(&DataPermission, Operation) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.authorize = Arc::new(authorize);
+        self
+    }
+
+    pub fn build(self) -> Router<AppState> {
+        let this = Arc::new(self);
+
+        Router::new()
+            .route(
+                "/",
+                routing::get({
+                    let this = this.clone();
+                    move |state, permission, query| this.list(state, permission, query)
+                })
+                .post({
+                    let this = this.clone();
+                    move |state, permission, params| this.create(state, permission, params)
+                }),
+            )
+            .route(
+                "/{id}",
+                routing::get({
+                    let this = this.clone();
+                    move |state, permission, id| this.get(state, permission, id)
+                })
+                .put({
+                    let this = this.clone();
+                    move |state, permission, id, params| this.update(state, permission, id, params)
+                })
+                .delete({
+                    let this = this.clone();
+                    move |state, permission, id| this.delete(state, permission, id)
+                }),
+            )
+    }
+
+    fn check(&self, permission: &DataPermission, operation: Operation) -> ApiResult<()> {
+        if (self.authorize)(permission, operation) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden)
+        }
+    }
+
+    async fn after_commit(&self, db: &DatabaseConnection) {
+        if let Err(e) = self.hooks.after_commit(db).await {
+            error!(
+                table = E::default().table_name(),
+                error = ?e,
+                "A hook failed after the write committed"
+            );
+        }
+    }
+
+    fn key() -> E::Column {
+        E::PrimaryKey::iter()
+            .next()
+            .expect("Entity should have a primary key")
+            .into_column()
+    }
+
+    async fn find(db: &impl ConnectionTrait, id: String) -> ApiResult<E::Model> {
+        E::find_by_id(id)
+            .one(db)
+            .await
+            .with_context(|| format!("Find {} by id", E::default().table_name()))?
+            .ok_or(ApiError::NotFound)
+    }
+
+    async fn list(
+        self: Arc<Self>,
+        State(AppState { db, .. }): State<AppState>,
+        permission: DataPermission,
+        ValidQuery(query): ValidQuery<QuerySpec<F>>,
+    ) -> ApiReturn<Paged<E::Model>> {
+        self.check(&permission, Operation::List)?;
+        let (select, pagination) = query.into_select();
+
+        let page = pagination
+            .fetch(select, Self::key(), &db)
+            .await
+            .with_context(|| format!("Failed to fetch {}", E::default().table_name()))?;
+
+        Ok(ApiResponse::success(page))
+    }
+
+    async fn get(
+        self: Arc<Self>,
+        State(AppState { db, .. }): State<AppState>,
+        permission: DataPermission,
+        Path(id): Path<String>,
+    ) -> ApiReturn<E::Model> {
+        self.check(&permission, Operation::Get)?;
+
+        Ok(ApiResponse::success(Self::find(&db, id).await?))
+    }
+
+    async fn create(
+        self: Arc<Self>,
+        State(AppState { db, .. }): State<AppState>,
+        permission: DataPermission,
+        ValidJson(params): ValidJson<C>,
+    ) -> ApiReturn<E::Model> {
+        self.check(&permission, Operation::Create)?;
+        let mut active_model = params.into_active_model();
+        let txn = db.begin().await.context("Begin transaction")?;
+        self.hooks
+            .before_save(&txn, &mut active_model, true)
+            .await?;
+
+        let model = active_model
+            .insert(&txn)
+            .await
+            .with_context(|| format!("Create {}", E::default().table_name()))?;
+        self.hooks.after_save(&txn, &model, true).await?;
+        txn.commit().await.context("Commit transaction")?;
+        self.after_commit(&db).await;
+
+        Ok(ApiResponse::success(model))
+    }
+
+    async fn update(
+        self: Arc<Self>,
+        State(AppState { db, .. }): State<AppState>,
+        permission: DataPermission,
+        Path(id): Path<String>,
+        ValidJson(params): ValidJson<U>,
+    ) -> ApiReturn<E::Model> {
+        self.check(&permission, Operation::Update)?;
+        let txn = db.begin().await.context("Begin transaction")?;
+        let mut active_model = Self::find(&txn, id).await?.into_active_model();
+
+        params.apply_to(&mut active_model);
+        self.hooks
+            .before_save(&txn, &mut active_model, false)
+            .await?;
+
+        let model = active_model
+            .update(&txn)
+            .await
+            .with_context(|| format!("Update {}", E::default().table_name()))?;
+        self.hooks.after_save(&txn, &model, false).await?;
+        txn.commit().await.context("Commit transaction")?;
+        self.after_commit(&db).await;
+
+        Ok(ApiResponse::success(model))
+    }
+
+    async fn delete(
+        self: Arc<Self>,
+        State(AppState { db, .. }): State<AppState>,
+        permission: DataPermission,
+        Path(id): Path<String>,
+    ) -> ApiReturn<()> {
+        self.check(&permission, Operation::Delete)?;
+        let txn = db.begin().await.context("Begin transaction")?;
+        let model = Self::find(&txn, id.clone()).await?;
+        self.hooks.before_delete(&txn, &model).await?;
+
+        E::delete_by_id(id)
+            .exec(&txn)
+            .await
+            .with_context(|| format!("Delete {}", E::default().table_name()))?;
+        self.hooks.after_delete(&txn, &model).await?;
+        txn.commit().await.context("Commit transaction")?;
+        self.after_commit(&db).await;
+
+        Ok(ApiResponse::success(()))
+    }
+}
@@ -1,13 +1,18 @@
 use argon2::password_hash::Error;
 use axum::{
     Json,
-    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    extract::{
+        multipart::{MultipartError, MultipartRejection},
+        rejection::{BytesRejection, JsonRejection, PathRejection, QueryRejection},
+    },
     response::{IntoResponse, Response},
 };
 use axum_extra::typed_header::TypedHeaderRejection;
 use axum_valid::ValidRejection;
 use serde::Serialize;
 
+use crate::app::request_id;
+
 pub type ApiResult<T> = Result<T, ApiError>;
 
 #[derive(Debug, thiserror::Error)]
@@ -22,6 +27,16 @@ pub enum ApiError {
     InvalidPathParams(#[from] PathRejection),
     #[error("Invalid json body: {0}")]
     InvalidJsonBody(#[from] JsonRejection),
+    #[error("Invalid request body: {0}")]
+    InvalidBody(#[from] BytesRejection),
+    #[error("Invalid multipart body: {0}")]
+    InvalidMultipart(#[from] MultipartRejection),
+    #[error("Invalid multipart field: {0}")]
+    InvalidMultipartField(#[from] MultipartError),
+    #[error("Payload too large, the limit is {0} bytes")]
+    PayloadTooLarge(usize),
+    #[error("Unsupported media type, expected {0}")]
+    UnsupportedMediaType(&'static str),
     #[error("Validation error: {0}")]
     ValidationError(String),
     #[error("Failed to hash password: {0}")]
@@ -30,6 +45,14 @@ pub enum ApiError {
     JwtError(#[from] jsonwebtoken::errors::Error),
     #[error("Failed to extract typed header: {0}")]
     TypedHeaderError(#[from] TypedHeaderRejection),
+    #[error("Invalid header: {0}")]
+    InvalidHeader(String),
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Precondition Failed: the resource has been modified")]
+    PreconditionFailed,
     #[error("Account or Password is incorrect")]
     LoginError,
     #[error("Internal Server Error")]
@@ -55,6 +78,9 @@ impl From<Error> for ApiError {
 pub struct ErrorResponse {
     pub code: u16,
     pub error: String,
+    /// The `X-Request-Id` of the request, to find the error in the logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl ApiError {
@@ -63,10 +89,21 @@ impl ApiError {
             ApiError::NotFound => axum::http::StatusCode::NOT_FOUND,
             ApiError::HashPassword(_) => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::MethodNotAllowed => axum::http::StatusCode::METHOD_NOT_ALLOWED,
+            // A body over the limit stays 413 behind the JSON rejection.
+            ApiError::InvalidJsonBody(JsonRejection::BytesRejection(e)) => e.status(),
             ApiError::InvalidQueryParams(_)
             | ApiError::InvalidPathParams(_)
-            | ApiError::InvalidJsonBody(_) => axum::http::StatusCode::BAD_REQUEST,
+            | ApiError::InvalidJsonBody(_)
+            | ApiError::InvalidHeader(_) => axum::http::StatusCode::BAD_REQUEST,
+            ApiError::InvalidBody(e) => e.status(),
+            ApiError::InvalidMultipart(e) => e.status(),
+            ApiError::InvalidMultipartField(e) => e.status(),
+            ApiError::PayloadTooLarge(_) => axum::http::StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::UnsupportedMediaType(_) => axum::http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
             ApiError::ValidationError(_) => axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Forbidden => axum::http::StatusCode::FORBIDDEN,
+            ApiError::Conflict(_) => axum::http::StatusCode::CONFLICT,
+            ApiError::PreconditionFailed => axum::http::StatusCode::PRECONDITION_FAILED,
             ApiError::Internal(e) => {
                 tracing::warn!(error = ?e, "Internal server error");
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR
@@ -84,6 +121,7 @@ impl IntoResponse for ApiError {
         let body = Json(ErrorResponse {
             code: status.as_u16(),
             error: self.to_string(),
+            request_id: request_id::current(),
         });
 
         (status, body).into_response()
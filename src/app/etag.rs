@@ -0,0 +1,194 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, HeaderName, request::Parts},
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{ETag, IfMatch, IfNoneMatch},
+};
+
+use crate::app::error::{ApiError, ApiResult};
+
+/// A resource whose representation changes whenever it is written, so that
+/// a strong `ETag` can be derived from it.
+pub trait Versioned {
+    fn version(&self) -> String;
+
+    fn etag(&self) -> ETag {
+        format!("\"{}\"", self.version())
+            .parse()
+            .expect("Version should be a valid entity tag")
+    }
+}
+
+/// The `If-Match` and `If-None-Match` headers of a request.
+#[derive(Debug, Default)]
+pub struct Preconditions {
+    if_match: Option<IfMatch>,
+    if_none_match: Option<IfNoneMatch>,
+}
+
+impl Preconditions {
+    /// Whether the client asked to write against a specific version.
+    pub fn is_conditional(&self) -> bool {
+        self.if_match.is_some()
+    }
+
+    /// Fails with `412 Precondition Failed` unless `If-Match` is absent or
+    /// matches the current version.
+    pub fn check_write(&self, current: &ETag) -> ApiResult<()> {
+        match &self.if_match {
+            Some(if_match) if !if_match.precondition_passes(current) => {
+                Err(ApiError::PreconditionFailed)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the client's cached copy is current per `If-None-Match`.
+    pub fn is_not_modified(&self, current: &ETag) -> bool {
+        self.if_none_match
+            .as_ref()
+            .is_some_and(|if_none_match| !if_none_match.precondition_passes(current))
+    }
+}
+
+impl<S> FromRequestParts<S> for Preconditions
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self {
+            if_match: optional_header(parts, state).await?,
+            if_none_match: optional_header(parts, state).await?,
+        })
+    }
+}
+
+/// Entity tag ranges decode successfully from zero values, so absence has
+/// to be checked before decoding.
+async fn optional_header<T, S>(parts: &mut Parts, state: &S) -> ApiResult<Option<T>>
+where
+    T: axum_extra::headers::Header,
+    S: Send + Sync,
+{
+    if !parts.headers.contains_key(T::name()) {
+        return Ok(None);
+    }
+    check_entity_tags(&parts.headers, T::name())?;
+    let TypedHeader(header) = TypedHeader::<T>::from_request_parts(parts, state)
+        .await
+        .map_err(|e| ApiError::InvalidHeader(e.to_string()))?;
+    Ok(Some(header))
+}
+
+/// Rejects a header that is neither `*` nor a list of entity tags. Matching
+/// would silently skip malformed tags, turning a client's mistake into a
+/// failed precondition.
+fn check_entity_tags(headers: &HeaderMap, name: &HeaderName) -> ApiResult<()> {
+    let invalid = || ApiError::InvalidHeader(format!("{name} must be `*` or entity tags"));
+
+    for value in headers.get_all(name) {
+        let value = value.to_str().map_err(|_| invalid())?;
+        if value.trim() == "*" {
+            continue;
+        }
+        // Entity tags may themselves contain commas, so only split outside
+        // of quotes.
+        let mut quoted = false;
+        let mut start = 0;
+        for (index, char) in value.char_indices().chain([(value.len(), ',')]) {
+            match char {
+                '"' => quoted = !quoted,
+                ',' if !quoted => {
+                    let tag = value[start..index].trim();
+                    // Empty list elements are allowed and ignored.
+                    if !tag.is_empty() && tag.parse::<ETag>().is_err() {
+                        return Err(invalid());
+                    }
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+        if quoted {
+            return Err(invalid());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Request, StatusCode};
+
+    use super::*;
+
+    struct Row(&'static str);
+
+    impl Versioned for Row {
+        fn version(&self) -> String {
+            self.0.to_string()
+        }
+    }
+
+    async fn preconditions(headers: &[(&str, &[u8])]) -> ApiResult<Preconditions> {
+        let mut request = Request::builder();
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let (mut parts, ()) = request.body(()).unwrap().into_parts();
+        Preconditions::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn writes_without_if_match_are_unconditional() {
+        let preconditions = preconditions(&[]).await.unwrap();
+        assert!(!preconditions.is_conditional());
+        assert!(preconditions.check_write(&Row("1").etag()).is_ok());
+        assert!(!preconditions.is_not_modified(&Row("1").etag()));
+    }
+
+    #[tokio::test]
+    async fn if_match_rejects_other_versions() {
+        let preconditions = preconditions(&[("if-match", b"\"1\"")]).await.unwrap();
+        assert!(preconditions.is_conditional());
+        assert!(preconditions.check_write(&Row("1").etag()).is_ok());
+        assert!(matches!(
+            preconditions.check_write(&Row("2").etag()),
+            Err(ApiError::PreconditionFailed)
+        ));
+
+        let any = super::tests::preconditions(&[("if-match", b"*")])
+            .await
+            .unwrap();
+        assert!(any.check_write(&Row("2").etag()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn if_none_match_detects_current_copies() {
+        let preconditions = preconditions(&[("if-none-match", b"\"1\", \"2\"")])
+            .await
+            .unwrap();
+        assert!(preconditions.is_not_modified(&Row("2").etag()));
+        assert!(!preconditions.is_not_modified(&Row("3").etag()));
+
+        let weak = super::tests::preconditions(&[("if-none-match", b"W/\"a,b\", ")])
+            .await
+            .unwrap();
+        assert!(weak.is_not_modified(&"\"a,b\"".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn malformed_headers_are_bad_requests() {
+        for name in ["if-match", "if-none-match"] {
+            for value in [&b"not-quoted"[..], b"\"1\", 2", b"*, \"1\"", b"\"1"] {
+                let error = preconditions(&[(name, value)]).await.unwrap_err();
+                assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+            }
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use axum::{
+    body::Bytes,
     extract::{FromRequest, FromRequestParts, Request},
-    http::request::Parts,
+    http::{header, request::Parts},
 };
 use axum_valid::HasValidate;
+use serde::de::DeserializeOwned;
 
 use crate::app::error::ApiError;
 
@@ -76,6 +78,39 @@ pub struct Json<T>(pub T);
 
 impl_validate!(Json);
 
+pub const MERGE_PATCH_JSON: &str = "application/merge-patch+json";
+
+/// A JSON Merge Patch (RFC 7396) body, only accepted with the
+/// `application/merge-patch+json` content type.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MergePatch<T>(pub T);
+
+impl<T, S> FromRequest<S> for MergePatch<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_merge_patch = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case(MERGE_PATCH_JSON));
+        if !is_merge_patch {
+            return Err(ApiError::UnsupportedMediaType(MERGE_PATCH_JSON));
+        }
+
+        let bytes = Bytes::from_request(req, state).await?;
+        let axum::Json(value) = axum::Json::from_bytes(&bytes)?;
+        Ok(Self(value))
+    }
+}
+
+impl_validate!(MergePatch);
+
 #[derive(Debug, Clone, Copy, Default, FromRequest, FromRequestParts)]
 #[from_request(via(axum_valid::Valid), rejection(ApiError))]
 pub struct Valid<T>(pub T);
@@ -90,7 +125,11 @@ pub struct ValidQuery<T>(pub T);
 #[derive(Debug, Clone, Copy, Default)]
 pub struct ValidJson<T>(pub T);
 
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidMergePatch<T>(pub T);
+
 impl_validate_request!(ValidQuery, Query, FromRequestParts);
 impl_validate_request!(ValidPath, Path, FromRequestParts);
 impl_validate_request!(ValidJson, Json, FromRequestParts);
 impl_validate_request!(ValidJson, Json, FromRequest);
+impl_validate_request!(ValidMergePatch, MergePatch, FromRequest);
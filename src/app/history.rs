@@ -0,0 +1,38 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Field-level differences between two snapshots of a record, as a pair of
+/// `(before, after)` objects holding only the fields that changed.
+///
+/// Both snapshots go through `Serialize`, so `skip_serializing` fields such
+/// as passwords never show up. Fields listed in `ignore` are left out too.
+pub fn diff<T: Serialize>(
+    before: &T,
+    after: &T,
+    ignore: &[&str],
+) -> serde_json::Result<Option<(Value, Value)>> {
+    let (Value::Object(before), Value::Object(mut after)) =
+        (serde_json::to_value(before)?, serde_json::to_value(after)?)
+    else {
+        return Ok(None);
+    };
+
+    let mut old = Map::new();
+    let mut new = Map::new();
+    for (key, value) in before {
+        if ignore.contains(&key.as_str()) {
+            continue;
+        }
+        let changed = after.remove(&key).unwrap_or(Value::Null);
+        if value != changed {
+            old.insert(key.clone(), value);
+            new.insert(key, changed);
+        }
+    }
+
+    if old.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some((Value::Object(old), Value::Object(new))))
+    }
+}
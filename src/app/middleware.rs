@@ -3,12 +3,14 @@ use std::pin::Pin;
 use axum::{
     RequestExt,
     body::Body,
-    http::{Request, Response},
+    extract::Query,
+    http::{Request, Response, header},
 };
 use axum_extra::{
     TypedHeader,
     headers::{Authorization, authorization::Bearer},
 };
+use serde::Deserialize;
 use tower_http::auth::AsyncAuthorizeRequest;
 
 use crate::app::{auth, error::ApiError};
@@ -31,12 +33,15 @@ impl AsyncAuthorizeRequest<Body> for AuthLayer {
 
     fn authorize(&mut self, mut request: Request<Body>) -> Self::Future {
         Box::pin(async move {
-            let TypedHeader(Authorization(bearer)) = request
+            let token = match request
                 .extract_parts::<TypedHeader<Authorization<Bearer>>>()
                 .await
-                .map_err(ApiError::TypedHeaderError)?;
+            {
+                Ok(TypedHeader(Authorization(bearer))) => bearer.token().to_string(),
+                Err(e) => query_token(&request).ok_or(ApiError::TypedHeaderError(e))?,
+            };
 
-            let principal = auth::jwt_service().decode(bearer.token())?;
+            let principal = auth::jwt_service().decode(&token)?;
 
             request.extensions_mut().insert(principal);
 
@@ -44,3 +49,29 @@ impl AsyncAuthorizeRequest<Body> for AuthLayer {
         })
     }
 }
+
+/// The `access_token` query parameter of `EventSource` and WebSocket
+/// requests, which browsers cannot add an `Authorization` header to. Other
+/// requests must use the header, keeping tokens out of URLs and access logs.
+fn query_token(request: &Request<Body>) -> Option<String> {
+    let headers = request.headers();
+    let streaming = headers
+        .get(header::UPGRADE)
+        .is_some_and(|upgrade| upgrade.as_bytes().eq_ignore_ascii_case(b"websocket"))
+        || headers
+            .get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .is_some_and(|accept| accept.contains("text/event-stream"));
+    if !streaming {
+        return None;
+    }
+
+    #[derive(Deserialize)]
+    struct TokenQuery {
+        access_token: String,
+    }
+
+    Query::<TokenQuery>::try_from_uri(request.uri())
+        .ok()
+        .map(|Query(query)| query.access_token)
+}
@@ -1,51 +1,103 @@
+use std::sync::Arc;
+
 use axum::Router;
 use sea_orm::DatabaseConnection;
 use tracing::info;
 
 use crate::{
-    app::{error::ApiResult, response::ApiResponse},
-    config, database, logger,
+    app::{
+        error::ApiResult,
+        response::{ApiResponse, Tagged},
+    },
+    config, database, dict,
+    event::{self, EventBus},
+    job::Scheduler,
+    logger,
+    queue::Queue,
+    setting,
+    storage::{self, FileStorage},
 };
 
 pub mod auth;
+pub mod crud;
 pub mod error;
+pub mod etag;
 pub mod extract;
+pub mod history;
 mod latency;
 pub mod middleware;
 pub mod params;
+pub mod patch;
+pub mod permission;
+pub mod request_id;
 pub mod response;
+pub mod scope;
 mod server;
+pub mod upload;
 pub mod util;
 pub mod validation;
 
 pub type ApiReturn<T> = ApiResult<ApiResponse<T>>;
+pub type TaggedReturn<T> = ApiResult<Tagged<T>>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: DatabaseConnection,
+    pub storage: Arc<dyn FileStorage>,
+    pub events: Arc<EventBus>,
 }
 
 impl AppState {
-    pub fn new(db: DatabaseConnection) -> Self {
-        Self { db }
+    pub fn new(db: DatabaseConnection, storage: Arc<dyn FileStorage>, events: EventBus) -> Self {
+        Self {
+            db,
+            storage,
+            events: Arc::new(events),
+        }
     }
 }
 
 pub async fn run(router: Router<AppState>) -> anyhow::Result<()> {
-    logger::init();
+    let logger = logger::init()?;
     info!("Starting application...");
 
     let db = database::init().await?;
     info!("Database connection established");
 
-    let state = AppState::new(db);
+    dict::init(&db).await?;
+    info!("Dictionaries loaded");
+
+    setting::reload(&db).await?;
+    info!("Settings loaded");
+
+    let storage = storage::init().await?;
+    info!("File storage initialized");
+
+    let state = AppState::new(db, storage, event::bus());
+
+    let scheduler = Scheduler::start(state.clone()).await?;
+    info!("Job scheduler started");
+
+    let queue = Queue::start(state.clone())?;
+    info!("Task queue started");
+
     let server = server::Server::new(config::get().server());
 
-    tokio::select! {
+    let result = tokio::select! {
         res = server.start(router, state) => res,
         _ = tokio::signal::ctrl_c() => {
             info!("Received Ctrl+C, shutting down...");
             Ok(())
         }
-    }
+    };
+
+    scheduler.shutdown().await;
+    info!("Job scheduler stopped");
+
+    queue.shutdown().await;
+    info!("Task queue stopped");
+
+    logger.shutdown().await;
+
+    result
 }
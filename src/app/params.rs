@@ -1,6 +1,17 @@
-use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    fmt::{Debug, Display},
+    str::FromStr,
+};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use sea_orm::{
+    Condition, ConnectionTrait, DbErr, EntityTrait, IdenStatic, ModelTrait, Order, PaginatorTrait,
+    QueryFilter, QueryOrder, Select, Value,
+};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
-use validator::Validate;
+use validator::{Validate, ValidationError, ValidationErrors};
 
 #[serde_as]
 #[derive(Debug, Deserialize, Validate)]
@@ -48,3 +59,518 @@ impl<T> Page<T> {
         Self::new(page, page_size, total, items)
     }
 }
+
+#[serde_as]
+#[derive(Debug, Deserialize, Validate)]
+pub struct CursorParams {
+    pub cursor: Option<PageCursor>,
+    #[validate(range(min = 1, max = 100, message = "Limit must be between 1 and 100"))]
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(default = "ten")]
+    pub limit: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(default)]
+    pub with_total: bool,
+}
+
+/// An opaque keyset position, pointing either forwards or backwards from
+/// the key of the item it was taken from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageCursor {
+    Next(CursorKey),
+    Prev(CursorKey),
+}
+
+/// The keyset column value of the item a cursor was taken from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CursorKey {
+    String(String),
+    Integer(i64),
+}
+
+impl CursorKey {
+    fn of(value: Value) -> Result<Self, DbErr> {
+        let integer = match value {
+            Value::String(Some(key)) => return Ok(CursorKey::String(*key)),
+            Value::TinyInt(Some(key)) => key.into(),
+            Value::SmallInt(Some(key)) => key.into(),
+            Value::Int(Some(key)) => key.into(),
+            Value::BigInt(Some(key)) => key,
+            Value::TinyUnsigned(Some(key)) => key.into(),
+            Value::SmallUnsigned(Some(key)) => key.into(),
+            Value::Unsigned(Some(key)) => key.into(),
+            Value::BigUnsigned(Some(key)) => key
+                .try_into()
+                .map_err(|_| DbErr::Type(format!("Keyset key {key} is out of range")))?,
+            value => {
+                return Err(DbErr::Type(format!(
+                    "Keyset keys must be strings or integers, got {value:?}"
+                )));
+            }
+        };
+        Ok(CursorKey::Integer(integer))
+    }
+}
+
+impl From<CursorKey> for Value {
+    fn from(key: CursorKey) -> Self {
+        match key {
+            CursorKey::String(key) => key.into(),
+            CursorKey::Integer(key) => key.into(),
+        }
+    }
+}
+
+impl Display for PageCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (direction, key) = match self {
+            PageCursor::Next(key) => ("n", key),
+            PageCursor::Prev(key) => ("p", key),
+        };
+        let raw = match key {
+            CursorKey::String(key) => format!("{direction}:s:{key}"),
+            CursorKey::Integer(key) => format!("{direction}:i:{key}"),
+        };
+        f.write_str(&URL_SAFE_NO_PAD.encode(raw))
+    }
+}
+
+impl FromStr for PageCursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw = URL_SAFE_NO_PAD
+            .decode(s)
+            .ok()
+            .and_then(|raw| String::from_utf8(raw).ok())
+            .ok_or_else(|| "invalid cursor".to_string())?;
+
+        let invalid = || "invalid cursor".to_string();
+        let (direction, raw) = raw.split_once(':').ok_or_else(invalid)?;
+        let key = match raw.split_once(':').ok_or_else(invalid)? {
+            ("s", key) => CursorKey::String(key.to_string()),
+            ("i", key) => CursorKey::Integer(key.parse().map_err(|_| invalid())?),
+            _ => return Err(invalid()),
+        };
+        match direction {
+            "n" => Ok(PageCursor::Next(key)),
+            "p" => Ok(PageCursor::Prev(key)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PageCursor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for PageCursor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Either `page`/`page_size` or `cursor`/`limit`, chosen by which of the
+/// parameters the client sent.
+#[derive(Debug)]
+pub enum Pagination {
+    Offset(QueryParams),
+    Keyset(CursorParams),
+}
+
+impl Pagination {
+    pub fn is_keyset(&self) -> bool {
+        matches!(self, Pagination::Keyset(_))
+    }
+
+    /// Fetches one page of `select`, using `key` as the keyset column in
+    /// cursor mode. Keys must be time-ordered, like the UUIDv7 ids, so that
+    /// cursor pages come back newest first.
+    pub async fn fetch<E, C>(
+        self,
+        select: Select<E>,
+        key: E::Column,
+        db: &C,
+    ) -> Result<Paged<E::Model>, DbErr>
+    where
+        E: EntityTrait,
+        E::Model: Sync,
+        C: ConnectionTrait,
+    {
+        match self {
+            Pagination::Offset(pagination) => {
+                let paginator = select.paginate(db, pagination.page_size);
+                let total = paginator.num_items().await?;
+                let items = paginator.fetch_page(pagination.page - 1).await?;
+
+                Ok(Paged::Offset(Page::from_pagination(
+                    pagination, total, items,
+                )))
+            }
+            Pagination::Keyset(params) => {
+                let total = if params.with_total {
+                    Some(select.clone().count(db).await?)
+                } else {
+                    None
+                };
+
+                let mut cursor = select.cursor_by(key);
+                let (items, has_more) = match &params.cursor {
+                    None | Some(PageCursor::Next(_)) => {
+                        if let Some(PageCursor::Next(after)) = &params.cursor {
+                            cursor.before(Value::from(after.clone()));
+                        }
+                        let mut items = cursor.last(params.limit + 1).all(db).await?;
+                        items.reverse();
+                        let has_more = items.len() as u64 > params.limit;
+                        items.truncate(params.limit as usize);
+                        (items, has_more)
+                    }
+                    Some(PageCursor::Prev(before)) => {
+                        let mut items = cursor
+                            .after(Value::from(before.clone()))
+                            .first(params.limit + 1)
+                            .all(db)
+                            .await?;
+                        let has_more = items.len() as u64 > params.limit;
+                        items.truncate(params.limit as usize);
+                        items.reverse();
+                        (items, has_more)
+                    }
+                };
+
+                let key_of = |model: &E::Model| CursorKey::of(model.get(key));
+                let backwards = matches!(params.cursor, Some(PageCursor::Prev(_)));
+                let next_cursor = if backwards || has_more {
+                    items.last().map(key_of).transpose()?.map(PageCursor::Next)
+                } else {
+                    None
+                };
+                let prev_cursor = if (backwards && has_more)
+                    || matches!(params.cursor, Some(PageCursor::Next(_)))
+                {
+                    items.first().map(key_of).transpose()?.map(PageCursor::Prev)
+                } else {
+                    None
+                };
+                Ok(Paged::Cursor(CursorPage {
+                    limit: params.limit,
+                    total,
+                    next_cursor,
+                    prev_cursor,
+                    items,
+                }))
+            }
+        }
+    }
+}
+
+impl Validate for Pagination {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        match self {
+            Pagination::Offset(params) => params.validate(),
+            Pagination::Keyset(params) => params.validate(),
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Deserialize)]
+struct RawPagination {
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    page: Option<u64>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    page_size: Option<u64>,
+    cursor: Option<PageCursor>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    limit: Option<u64>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    with_total: Option<bool>,
+}
+
+impl<'de> Deserialize<'de> for Pagination {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawPagination::deserialize(deserializer)?;
+        let keyset = raw.cursor.is_some() || raw.limit.is_some() || raw.with_total.is_some();
+
+        if !keyset {
+            return Ok(Pagination::Offset(QueryParams {
+                page: raw.page.unwrap_or_else(one),
+                page_size: raw.page_size.unwrap_or_else(ten),
+            }));
+        }
+        if raw.page.is_some() || raw.page_size.is_some() {
+            return Err(serde::de::Error::custom(
+                "page/page_size cannot be combined with cursor/limit",
+            ));
+        }
+
+        Ok(Pagination::Keyset(CursorParams {
+            cursor: raw.cursor,
+            limit: raw.limit.unwrap_or_else(ten),
+            with_total: raw.with_total.unwrap_or_default(),
+        }))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CursorPage<T> {
+    pub limit: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    pub next_cursor: Option<PageCursor>,
+    pub prev_cursor: Option<PageCursor>,
+    pub items: Vec<T>,
+}
+
+/// A page of a list endpoint in whichever pagination mode was requested.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Paged<T> {
+    Offset(Page<T>),
+    Cursor(CursorPage<T>),
+}
+
+/// Whitelist of columns a list endpoint may be sorted by, keyed by the
+/// camelCase field names used in responses.
+pub trait Sortable: EntityTrait {
+    fn sort_column(field: &str) -> Option<Self::Column>;
+
+    fn default_sort() -> Vec<(Self::Column, Order)>;
+}
+
+/// Filters of a list endpoint, turned into a `Condition` on its entity.
+pub trait Filter {
+    type Entity: Sortable;
+
+    fn condition(&self) -> Condition;
+
+    /// Narrows `select` beyond `condition`, such as a full-text search that
+    /// orders by relevance when `ranked`.
+    fn search(&self, select: Select<Self::Entity>, _ranked: bool) -> Select<Self::Entity> {
+        select
+    }
+}
+
+/// A `sort=-createdAt,name` parameter, a leading `-` meaning descending.
+pub struct Sort<E: Sortable>(Vec<(E::Column, Order)>);
+
+impl<E: Sortable> Sort<E> {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn apply(self, mut select: Select<E>) -> Select<E> {
+        let orders = if self.0.is_empty() {
+            E::default_sort()
+        } else {
+            self.0
+        };
+        for (column, order) in orders {
+            select = select.order_by(column, order);
+        }
+        select
+    }
+}
+
+impl<E: Sortable> Default for Sort<E> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<E: Sortable> Debug for Sort<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|(column, order)| {
+                let prefix = if matches!(order, Order::Desc) {
+                    "-"
+                } else {
+                    ""
+                };
+                format!("{prefix}{}", column.as_str())
+            }))
+            .finish()
+    }
+}
+
+impl<E: Sortable> FromStr for Sort<E> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(|field| {
+                let (field, order) = match field.strip_prefix('-') {
+                    Some(field) => (field, Order::Desc),
+                    None => (field.strip_prefix('+').unwrap_or(field), Order::Asc),
+                };
+                E::sort_column(field)
+                    .map(|column| (column, order))
+                    .ok_or_else(|| format!("unknown sort field `{field}`"))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl<'de, E: Sortable> Deserialize<'de> for Sort<E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Filters, sorting and pagination of a list endpoint, extracted together
+/// with `ValidQuery<QuerySpec<F>>`.
+#[derive(Debug, Deserialize)]
+#[serde(bound(deserialize = "F: Deserialize<'de>"))]
+pub struct QuerySpec<F: Filter> {
+    #[serde(flatten)]
+    pub filter: F,
+    #[serde(default)]
+    pub sort: Sort<F::Entity>,
+    #[serde(flatten)]
+    pub pagination: Pagination,
+}
+
+impl<F: Filter + Validate> Validate for QuerySpec<F> {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        self.filter.validate()?;
+        if self.pagination.is_keyset() && !self.sort.is_empty() {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "sort",
+                ValidationError::new("sort_with_cursor").with_message(Cow::Borrowed(
+                    "Sorting is not supported with cursor pagination",
+                )),
+            );
+            return Err(errors);
+        }
+        self.pagination.validate()
+    }
+}
+
+impl<F: Filter> QuerySpec<F> {
+    /// Builds the filtered and sorted query, leaving pagination to the caller.
+    /// Cursor pages are always ordered by their keyset column, so the sort is
+    /// only applied to offset pages, and search relevance only ranks them
+    /// when no sort was requested.
+    pub fn into_select(self) -> (Select<F::Entity>, Pagination) {
+        let ranked = !self.pagination.is_keyset() && self.sort.is_empty();
+        let select = F::Entity::find().filter(self.filter.condition());
+        let select = self.filter.search(select, ranked);
+        let select = if self.pagination.is_keyset() {
+            select
+        } else {
+            self.sort.apply(select)
+        };
+        (select, self.pagination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database,
+        entity::{prelude::*, sys_notification},
+        notify::{self, Notice},
+    };
+
+    #[test]
+    fn cursors_round_trip() {
+        for cursor in [
+            PageCursor::Next(CursorKey::String("01a1:b".to_string())),
+            PageCursor::Prev(CursorKey::String(String::new())),
+            PageCursor::Next(CursorKey::Integer(-42)),
+            PageCursor::Prev(CursorKey::Integer(i64::MAX)),
+        ] {
+            assert_eq!(cursor.to_string().parse::<PageCursor>(), Ok(cursor));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        for raw in ["n:abc", "x:s:abc", "n:i:abc", "n:f:1.5", "n"] {
+            let cursor = URL_SAFE_NO_PAD.encode(raw);
+            assert!(cursor.parse::<PageCursor>().is_err(), "{raw}");
+        }
+        assert!("not base64!".parse::<PageCursor>().is_err());
+    }
+
+    #[test]
+    fn keys_cursors_by_strings_and_integers() {
+        assert_eq!(
+            CursorKey::of(Value::from("a")).unwrap(),
+            CursorKey::String("a".to_string())
+        );
+        assert_eq!(
+            CursorKey::of(Value::from(7_i32)).unwrap(),
+            CursorKey::Integer(7)
+        );
+        assert_eq!(
+            CursorKey::of(Value::from(7_u64)).unwrap(),
+            CursorKey::Integer(7)
+        );
+        assert!(CursorKey::of(Value::from(u64::MAX)).is_err());
+        assert!(CursorKey::of(Value::from(1.5_f64)).is_err());
+        assert!(CursorKey::of(Value::String(None)).is_err());
+    }
+
+    async fn page(
+        db: &sea_orm::DatabaseConnection,
+        cursor: Option<PageCursor>,
+    ) -> CursorPage<sys_notification::Model> {
+        let pagination = Pagination::Keyset(CursorParams {
+            cursor,
+            limit: 2,
+            with_total: false,
+        });
+        match pagination
+            .fetch(SysNotification::find(), sys_notification::Column::Id, db)
+            .await
+            .unwrap()
+        {
+            Paged::Cursor(page) => page,
+            Paged::Offset(_) => unreachable!(),
+        }
+    }
+
+    fn titles(page: &CursorPage<sys_notification::Model>) -> Vec<&str> {
+        page.items.iter().map(|item| item.title.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn pages_through_cursors_both_ways() {
+        let db = database::memory().await;
+        for title in ["1", "2", "3", "4", "5"] {
+            let _push = notify::notify(&db, "user", Notice::announcement(title, ""))
+                .await
+                .unwrap();
+        }
+
+        let first = page(&db, None).await;
+        assert_eq!(titles(&first), ["5", "4"]);
+        assert!(first.prev_cursor.is_none());
+        let second = page(&db, first.next_cursor).await;
+        assert_eq!(titles(&second), ["3", "2"]);
+        let last = page(&db, second.next_cursor.clone()).await;
+        assert_eq!(titles(&last), ["1"]);
+        assert!(last.next_cursor.is_none());
+
+        let back = page(&db, last.prev_cursor).await;
+        assert_eq!(titles(&back), ["3", "2"]);
+        let start = page(&db, back.prev_cursor).await;
+        assert_eq!(titles(&start), ["5", "4"]);
+        assert!(start.prev_cursor.is_none());
+    }
+}
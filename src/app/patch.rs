@@ -0,0 +1,158 @@
+use sea_orm::ActiveValue;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use validator::{ValidateLength, ValidateRequired};
+
+use crate::app::error::{ApiError, ApiResult};
+
+/// A field of a JSON Merge Patch (RFC 7396) document: absent fields are left
+/// unchanged, `null` clears the field and any other value replaces it.
+///
+/// Fields must be marked `#[serde(default)]` so that absent keys become
+/// `Missing`. On columns that cannot be cleared, use
+/// `#[validate(required)]` to reject `null`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Patch<T> {
+    #[default]
+    Missing,
+    Null,
+    Value(T),
+}
+
+impl<T> Patch<T> {
+    pub fn as_value(&self) -> Option<&T> {
+        match self {
+            Patch::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Runs a validator written for `T` on the patched value, if any.
+    pub fn validate_with<E>(&self, f: impl FnOnce(&T) -> Result<(), E>) -> Result<(), E> {
+        self.as_value().map_or(Ok(()), f)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Patch<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::<T>::deserialize(deserializer).map(|value| value.map_or(Patch::Null, Patch::Value))
+    }
+}
+
+impl<T: Serialize> Serialize for Patch<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_value().serialize(serializer)
+    }
+}
+
+impl<T> ValidateRequired for Patch<T> {
+    fn is_some(&self) -> bool {
+        !matches!(self, Patch::Null)
+    }
+}
+
+impl<T: ValidateLength<u64>> ValidateLength<u64> for Patch<T> {
+    fn length(&self) -> Option<u64> {
+        self.as_value().and_then(ValidateLength::length)
+    }
+}
+
+/// Applies a [`Patch`] onto the matching field of an `ActiveModel`.
+pub trait ApplyPatch<V: Into<sea_orm::Value>> {
+    fn apply(self, field: &'static str, target: &mut ActiveValue<V>) -> ApiResult<()>;
+}
+
+impl<T> ApplyPatch<T> for Patch<T>
+where
+    T: Into<sea_orm::Value>,
+{
+    fn apply(self, field: &'static str, target: &mut ActiveValue<T>) -> ApiResult<()> {
+        match self {
+            Patch::Missing => Ok(()),
+            Patch::Null => Err(ApiError::ValidationError(format!(
+                "{field}: cannot be null"
+            ))),
+            Patch::Value(value) => {
+                *target = ActiveValue::Set(value);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<T> ApplyPatch<Option<T>> for Patch<T>
+where
+    Option<T>: Into<sea_orm::Value>,
+{
+    fn apply(self, _field: &'static str, target: &mut ActiveValue<Option<T>>) -> ApiResult<()> {
+        match self {
+            Patch::Missing => {}
+            Patch::Null => *target = ActiveValue::Set(None),
+            Patch::Value(value) => *target = ActiveValue::Set(Some(value)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Params {
+        #[serde(default)]
+        name: Patch<String>,
+        #[serde(default)]
+        avatar: Patch<String>,
+    }
+
+    fn parse(json: &str) -> Params {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn tells_missing_null_and_values_apart() {
+        let params = parse(r#"{"avatar": null}"#);
+        assert_eq!(params.name, Patch::Missing);
+        assert_eq!(params.avatar, Patch::Null);
+
+        let params = parse(r#"{"name": "Ada", "avatar": "a.png"}"#);
+        assert_eq!(params.name, Patch::Value("Ada".to_string()));
+        assert_eq!(params.avatar.as_value().map(String::as_str), Some("a.png"));
+    }
+
+    #[test]
+    fn applies_onto_optional_columns() {
+        let current = || ActiveValue::Unchanged(Some("old.png".to_string()));
+
+        let mut target = current();
+        Patch::<String>::Missing
+            .apply("avatar", &mut target)
+            .unwrap();
+        assert_eq!(target, current());
+
+        Patch::<String>::Null.apply("avatar", &mut target).unwrap();
+        assert_eq!(target, ActiveValue::Set(None));
+
+        Patch::Value("new.png".to_string())
+            .apply("avatar", &mut target)
+            .unwrap();
+        assert_eq!(target, ActiveValue::Set(Some("new.png".to_string())));
+    }
+
+    #[test]
+    fn rejects_null_on_required_columns() {
+        let mut target = ActiveValue::Unchanged("Ada".to_string());
+        assert!(matches!(
+            Patch::<String>::Null.apply("name", &mut target),
+            Err(ApiError::ValidationError(message)) if message == "name: cannot be null"
+        ));
+        assert_eq!(target, ActiveValue::Unchanged("Ada".to_string()));
+
+        Patch::Value("Grace".to_string())
+            .apply("name", &mut target)
+            .unwrap();
+        assert_eq!(target, ActiveValue::Set("Grace".to_string()));
+    }
+}
@@ -0,0 +1,30 @@
+//! The permission keys checked by administrative endpoints. A role holds a
+//! key when it is granted a menu, usually a button, whose `permission` is the
+//! key. The key `*` stands for every permission, and accounts listed in
+//! `[auth] admin_accounts` hold it without any role.
+
+/// Every permission.
+pub const ALL: &str = "*";
+
+/// Assign roles to users.
+pub const USER_ROLES: &str = "system:user:roles";
+/// Create, change and delete roles and the menus they grant.
+pub const ROLE_EDIT: &str = "system:role:edit";
+/// Create, change, move and delete departments.
+pub const DEPT_EDIT: &str = "system:dept:edit";
+/// Create, change and delete dictionary types and data.
+pub const DICT_EDIT: &str = "system:dict:edit";
+/// Create, change and delete menus.
+pub const MENU_EDIT: &str = "system:menu:edit";
+/// Read, download and delete the files of every user.
+pub const FILE_MANAGE: &str = "system:file:manage";
+/// Read and change runtime settings.
+pub const SETTING_MANAGE: &str = "system:setting:manage";
+/// Send announcements to the inboxes of users.
+pub const NOTIFICATION_ANNOUNCE: &str = "system:notification:announce";
+/// Inspect, change and trigger scheduled jobs.
+pub const JOB_MANAGE: &str = "system:job:manage";
+/// Inspect and retry background tasks.
+pub const TASK_MANAGE: &str = "system:task:manage";
+/// Create, change and inspect webhooks and their deliveries.
+pub const WEBHOOK_MANAGE: &str = "system:webhook:manage";
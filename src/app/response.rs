@@ -1,6 +1,9 @@
-use axum::{Json, response::IntoResponse};
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use axum_extra::{TypedHeader, headers::ETag};
 use serde::{Deserialize, Serialize};
 
+use crate::app::etag::{Preconditions, Versioned};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
     pub message: String,
@@ -21,3 +24,37 @@ impl<T: Serialize> IntoResponse for ApiResponse<T> {
         (axum::http::StatusCode::OK, Json(self)).into_response()
     }
 }
+
+/// A single resource response carrying its `ETag`, or a bodiless
+/// `304 Not Modified` when the client's copy is still current.
+#[derive(Debug)]
+pub enum Tagged<T> {
+    Fresh(ETag, ApiResponse<T>),
+    NotModified(ETag),
+}
+
+impl<T: Versioned> Tagged<T> {
+    pub fn fresh(data: T) -> Self {
+        Self::Fresh(data.etag(), ApiResponse::success(data))
+    }
+
+    pub fn conditional(data: T, preconditions: &Preconditions) -> Self {
+        let etag = data.etag();
+        if preconditions.is_not_modified(&etag) {
+            Self::NotModified(etag)
+        } else {
+            Self::Fresh(etag, ApiResponse::success(data))
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Tagged<T> {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Tagged::Fresh(etag, response) => (TypedHeader(etag), response).into_response(),
+            Tagged::NotModified(etag) => {
+                (StatusCode::NOT_MODIFIED, TypedHeader(etag)).into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use axum::{extract::FromRequestParts, http::request::Parts};
+use sea_orm::{
+    Condition, DatabaseConnection, QuerySelect,
+    prelude::*,
+    sea_query::{Expr, Query},
+};
+
+use crate::{
+    app::{
+        AppState,
+        auth::Principal,
+        error::{ApiError, ApiResult},
+        permission,
+    },
+    config,
+    entity::{
+        data_scope::DataScope, prelude::*, sys_dept, sys_menu, sys_role, sys_role_menu, sys_user,
+        sys_user_role,
+    },
+};
+
+/// What the principal may access: the users granted by the widest data
+/// scope among their roles, and the permission keys of the menus granted to
+/// those roles. Users without roles fall back to the configured
+/// `default_data_scope`, while accounts listed in `admin_accounts` see every
+/// user and hold every permission.
+#[derive(Debug, Clone)]
+pub struct DataPermission {
+    user_id: String,
+    dept: Option<sys_dept::Model>,
+    scope: DataScope,
+    permissions: HashSet<String>,
+}
+
+impl DataPermission {
+    pub async fn load(db: &DatabaseConnection, principal: &Principal) -> anyhow::Result<Self> {
+        let user = SysUser::find_by_id(&principal.id)
+            .one(db)
+            .await
+            .context("Find the principal")?;
+        let dept = match user.as_ref().and_then(|user| user.dept_id.as_deref()) {
+            Some(dept_id) => SysDept::find_by_id(dept_id)
+                .one(db)
+                .await
+                .context("Find the principal's department")?,
+            None => None,
+        };
+
+        let is_admin = user.as_ref().is_some_and(|user| {
+            config::get()
+                .auth()
+                .admin_accounts()
+                .contains(&user.account)
+        });
+        if is_admin {
+            return Ok(Self {
+                user_id: principal.id.clone(),
+                dept,
+                scope: DataScope::All,
+                permissions: HashSet::from([permission::ALL.to_string()]),
+            });
+        }
+
+        let role_ids = SysUserRole::find()
+            .filter(sys_user_role::Column::UserId.eq(&principal.id))
+            .all(db)
+            .await
+            .context("Find roles of the principal")?
+            .into_iter()
+            .map(|user_role| user_role.role_id)
+            .collect::<Vec<_>>();
+
+        let scope = SysRole::find()
+            .filter(sys_role::Column::Id.is_in(role_ids.clone()))
+            .all(db)
+            .await
+            .context("Find roles of the principal")?
+            .into_iter()
+            .map(|role| role.data_scope)
+            .max()
+            .unwrap_or_else(|| config::get().auth().default_data_scope());
+
+        let permissions = SysMenu::find()
+            .select_only()
+            .column(sys_menu::Column::Permission)
+            .filter(
+                sys_menu::Column::Id.in_subquery(
+                    Query::select()
+                        .column(sys_role_menu::Column::MenuId)
+                        .from(SysRoleMenu)
+                        .and_where(sys_role_menu::Column::RoleId.is_in(role_ids))
+                        .to_owned(),
+                ),
+            )
+            .filter(sys_menu::Column::Permission.is_not_null())
+            .into_tuple::<String>()
+            .all(db)
+            .await
+            .context("Find permissions of the principal")?
+            .into_iter()
+            .collect();
+
+        Ok(Self {
+            user_id: principal.id.clone(),
+            dept,
+            scope,
+            permissions,
+        })
+    }
+
+    /// Whether the principal holds the permission `key`.
+    pub fn has(&self, key: &str) -> bool {
+        self.permissions.contains(permission::ALL) || self.permissions.contains(key)
+    }
+
+    /// The permission keys granted to the principal.
+    pub fn permissions(&self) -> impl Iterator<Item = &str> {
+        self.permissions.iter().map(String::as_str)
+    }
+
+    /// Rejects principals without the permission `key`.
+    pub fn require(&self, key: &str) -> ApiResult<()> {
+        if self.has(key) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden)
+        }
+    }
+
+    /// Matches the `sys_user` rows within the scope. Users can always reach
+    /// themselves, and department scopes shrink to that when the principal
+    /// has no department.
+    pub fn user_condition(&self) -> Condition {
+        let own = sys_user::Column::Id.eq(&self.user_id);
+        match (self.scope, &self.dept) {
+            (DataScope::All, _) => Condition::all(),
+            (DataScope::DeptAndChildren, Some(dept)) => Condition::any().add(own).add(
+                sys_user::Column::DeptId.in_subquery(
+                    Query::select()
+                        .column(sys_dept::Column::Id)
+                        .from(SysDept)
+                        .and_where(sys_dept::subtree(&dept.id))
+                        .to_owned(),
+                ),
+            ),
+            (DataScope::Dept, Some(dept)) => Condition::any()
+                .add(own)
+                .add(sys_user::Column::DeptId.eq(&dept.id)),
+            _ => Condition::all().add(own),
+        }
+    }
+
+    /// Matches the `sys_dept` rows within the scope, which are the
+    /// departments the principal may assign users to. Narrower scopes than
+    /// the department ones match none.
+    pub fn dept_condition(&self) -> Condition {
+        match (self.scope, &self.dept) {
+            (DataScope::All, _) => Condition::all(),
+            (DataScope::DeptAndChildren, Some(dept)) => {
+                Condition::all().add(sys_dept::subtree(&dept.id))
+            }
+            (DataScope::Dept, Some(dept)) => {
+                Condition::all().add(sys_dept::Column::Id.eq(&dept.id))
+            }
+            _ => Condition::all().add(Expr::value(false)),
+        }
+    }
+}
+
+impl FromRequestParts<AppState> for DataPermission {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        AppState { db, .. }: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let principal = parts
+            .extensions
+            .get::<Principal>()
+            .context("Data permissions require an authenticated route")?;
+
+        Ok(Self::load(db, principal).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ActiveModelTrait, ActiveValue};
+
+    use super::*;
+    use crate::{database, entity::gender::Gender};
+
+    async fn dept(
+        db: &DatabaseConnection,
+        name: &str,
+        parent: Option<&sys_dept::Model>,
+    ) -> sys_dept::Model {
+        sys_dept::ActiveModel {
+            parent_id: ActiveValue::Set(parent.map(|parent| parent.id.clone())),
+            name: ActiveValue::Set(name.to_string()),
+            path: parent.map_or(ActiveValue::NotSet, |parent| {
+                ActiveValue::Set(parent.path.clone())
+            }),
+            sort: ActiveValue::Set(0),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap()
+    }
+
+    async fn user(
+        db: &DatabaseConnection,
+        account: &str,
+        dept: Option<&sys_dept::Model>,
+    ) -> sys_user::Model {
+        sys_user::ActiveModel {
+            name: ActiveValue::Set(account.to_string()),
+            gender: ActiveValue::Set(Gender::Female),
+            account: ActiveValue::Set(account.to_string()),
+            password: ActiveValue::Set(String::new()),
+            mobile_phone: ActiveValue::Set(String::new()),
+            birthday: ActiveValue::Set(chrono::NaiveDate::default()),
+            enabled: ActiveValue::Set(true),
+            dept_id: ActiveValue::Set(dept.map(|dept| dept.id.clone())),
+            created_at: ActiveValue::Set(chrono::NaiveDateTime::default()),
+            updated_at: ActiveValue::Set(chrono::NaiveDateTime::default()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap()
+    }
+
+    /// The accounts and department names within `permission`.
+    async fn visible(
+        db: &DatabaseConnection,
+        permission: &DataPermission,
+    ) -> (Vec<String>, Vec<String>) {
+        let mut accounts = SysUser::find()
+            .filter(permission.user_condition())
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|user| user.account)
+            .collect::<Vec<_>>();
+        accounts.sort();
+        let mut depts = SysDept::find()
+            .filter(permission.dept_condition())
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|dept| dept.name)
+            .collect::<Vec<_>>();
+        depts.sort();
+        (accounts, depts)
+    }
+
+    #[tokio::test]
+    async fn conditions_follow_the_data_scope() {
+        let db = database::memory().await;
+        let root = dept(&db, "root", None).await;
+        let sales = dept(&db, "sales", Some(&root)).await;
+        let east = dept(&db, "east", Some(&sales)).await;
+        let ops = dept(&db, "ops", Some(&root)).await;
+        let me = user(&db, "me", Some(&sales)).await;
+        user(&db, "colleague", Some(&sales)).await;
+        user(&db, "report", Some(&east)).await;
+        user(&db, "other", Some(&ops)).await;
+        user(&db, "unassigned", None).await;
+
+        let permission = |scope, dept: Option<&sys_dept::Model>| DataPermission {
+            user_id: me.id.clone(),
+            dept: dept.cloned(),
+            scope,
+            permissions: HashSet::new(),
+        };
+        let strings = |values: &[&str]| {
+            values
+                .iter()
+                .map(|value| String::from(*value))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            visible(&db, &permission(DataScope::All, Some(&sales))).await,
+            (
+                strings(&["colleague", "me", "other", "report", "unassigned"]),
+                strings(&["east", "ops", "root", "sales"])
+            )
+        );
+        assert_eq!(
+            visible(&db, &permission(DataScope::DeptAndChildren, Some(&sales))).await,
+            (
+                strings(&["colleague", "me", "report"]),
+                strings(&["east", "sales"])
+            )
+        );
+        assert_eq!(
+            visible(&db, &permission(DataScope::Dept, Some(&sales))).await,
+            (strings(&["colleague", "me"]), strings(&["sales"]))
+        );
+        assert_eq!(
+            visible(&db, &permission(DataScope::SelfOnly, Some(&sales))).await,
+            (strings(&["me"]), vec![])
+        );
+        // Department scopes shrink to the principal without a department.
+        assert_eq!(
+            visible(&db, &permission(DataScope::DeptAndChildren, None)).await,
+            (strings(&["me"]), vec![])
+        );
+    }
+
+    #[test]
+    fn all_permission_holds_every_key() {
+        let permission = |keys: &[&str]| DataPermission {
+            user_id: String::new(),
+            dept: None,
+            scope: DataScope::SelfOnly,
+            permissions: keys.iter().map(|key| String::from(*key)).collect(),
+        };
+
+        assert!(permission(&[permission::ALL]).has(permission::DEPT_EDIT));
+        assert!(permission(&[permission::DEPT_EDIT]).has(permission::DEPT_EDIT));
+        assert!(!permission(&[permission::DICT_EDIT]).has(permission::DEPT_EDIT));
+        assert!(matches!(
+            permission(&[]).require(permission::DEPT_EDIT),
+            Err(ApiError::Forbidden)
+        ));
+    }
+}
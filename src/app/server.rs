@@ -1,20 +1,32 @@
 use std::{
     net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    path::PathBuf,
+    sync::Arc,
     time::Duration,
 };
 
-use axum::{Router, extract::Request, http::StatusCode};
+use anyhow::{Context, bail};
+use async_trait::async_trait;
+use axum::{
+    Router,
+    extract::{DefaultBodyLimit, Request},
+    http::StatusCode,
+    middleware,
+};
 use axum_server::tls_rustls::RustlsConfig;
+use sea_orm::JsonValue;
+use serde_json::json;
 use tower_http::{
-    cors::CorsLayer, limit::RequestBodyLimitLayer, normalize_path::NormalizePathLayer,
-    timeout::TimeoutLayer, trace::TraceLayer,
+    cors::CorsLayer, normalize_path::NormalizePathLayer, timeout::TimeoutLayer, trace::TraceLayer,
 };
 use tracing::info;
-use uuid::Uuid;
+use x509_parser::pem::parse_x509_pem;
 
 use crate::{
-    app::{AppState, latency::LatencyLayer},
+    app::{AppState, latency::LatencyLayer, request_id},
     config::{self, server::ServerConfig},
+    health::{self, Check},
+    logger,
 };
 
 pub struct Server {
@@ -27,7 +39,19 @@ impl Server {
     }
 
     pub async fn start(&self, router: Router<AppState>, state: AppState) -> anyhow::Result<()> {
-        let router = self.build_router(router, state);
+        let metrics = config::get().metrics();
+        let router = self.build_router(router, state.clone());
+
+        if metrics.enable() && !metrics.public() {
+            tokio::try_join!(self.serve(router), serve_metrics(metrics.port(), state))?;
+        } else {
+            self.serve(router).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn serve(&self, router: Router) -> anyhow::Result<()> {
         let port = self.config.port();
 
         let address = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port));
@@ -36,6 +60,13 @@ impl Server {
         let ssl = config::get().ssl();
 
         if ssl.enable() {
+            health::register(Arc::new(Certificate {
+                path: ssl.cert_path().expect("read cert file").clone(),
+                min_days: config::get().health().certificate_days(),
+            }));
+            // reqwest enables rustls' ring provider next to axum-server's
+            // aws-lc-rs, so rustls cannot pick one by itself.
+            let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
             let config = RustlsConfig::from_pem_file(
                 ssl.cert_path().expect("read cert file"),
                 ssl.key_path().expect("read key file"),
@@ -55,21 +86,35 @@ impl Server {
     }
 
     fn build_router(&self, router: Router<AppState>, state: AppState) -> Router {
-        Router::new()
-            .merge(router)
+        let metrics = config::get().metrics();
+        let mut router = Router::new().merge(router);
+        if metrics.enable() {
+            router = router.route_layer(middleware::from_fn(crate::metrics::track));
+            if metrics.public() {
+                router = router.merge(crate::metrics::create_router());
+            }
+        }
+
+        router
             .layer(NormalizePathLayer::trim_trailing_slash())
-            .layer(RequestBodyLimitLayer::new(10 * 1024 * 1024))
+            // A default for the body extractors rather than a hard cap, so
+            // that upload routes can lift it for their own limits.
+            .layer(DefaultBodyLimit::max(10 * 1024 * 1024))
             .layer(
                 TraceLayer::new_for_http()
                     .make_span_with(|request: &Request| {
-                        let id = Uuid::new_v4();
-                        tracing::info_span!(
+                        let span = tracing::info_span!(
                             "http_request",
-                            id = %id,
+                            id = %request_id::from_headers(request.headers()),
                             method = %request.method(),
-                            uri = %request.uri(),
+                            // The query may carry the `access_token` of
+                            // SSE and WebSocket clients, so it stays out of
+                            // logs and exported traces.
+                            path = %request.uri().path(),
                             version = ?request.version(),
-                        )
+                        );
+                        logger::continue_trace(&span, request.headers());
+                        span
                     })
                     .on_request(())
                     .on_response(LatencyLayer)
@@ -80,6 +125,61 @@ impl Server {
                 StatusCode::REQUEST_TIMEOUT,
                 Duration::from_secs(30),
             ))
+            .layer(middleware::from_fn(request_id::propagate))
             .with_state(state)
     }
 }
+
+/// Serves `/metrics` alone over plain HTTP, for scrapers on an internal
+/// network.
+async fn serve_metrics(port: u16, state: AppState) -> anyhow::Result<()> {
+    let address = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port));
+    info!("serving metrics on http://{}/metrics", address);
+
+    axum_server::bind(address)
+        .serve(
+            crate::metrics::create_router()
+                .with_state(state)
+                .into_make_service(),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Fails when the TLS certificate expires within `min_days`. The file is
+/// read on every check, so a renewed certificate on disk is noticed, but the
+/// server only serves it after a restart.
+struct Certificate {
+    path: PathBuf,
+    min_days: i64,
+}
+
+#[async_trait]
+impl Check for Certificate {
+    fn name(&self) -> &'static str {
+        "certificate"
+    }
+
+    async fn check(&self, _state: &AppState) -> anyhow::Result<JsonValue> {
+        let pem = tokio::fs::read(&self.path)
+            .await
+            .with_context(|| format!("Read {}", self.path.display()))?;
+        let (_, pem) = parse_x509_pem(&pem).context("Parse the certificate PEM")?;
+        let certificate = pem.parse_x509().context("Parse the certificate")?;
+
+        let not_after = certificate.validity().not_after.timestamp();
+        let not_after = chrono::DateTime::from_timestamp(not_after, 0)
+            .context("The certificate expiry is out of range")?;
+        let days_left = (not_after - chrono::Utc::now()).num_days();
+        if days_left < self.min_days {
+            bail!("The certificate expires at {not_after}, in {days_left} days");
+        }
+
+        Ok(json!({
+            "subject": certificate.subject().to_string(),
+            "notAfter": not_after,
+            "daysLeft": days_left,
+        }))
+    }
+}
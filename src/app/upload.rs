@@ -0,0 +1,97 @@
+use std::io::Cursor;
+
+use anyhow::Context;
+use axum::{body::Bytes, extract::Multipart};
+use image::{ImageFormat, imageops::FilterType};
+use sea_orm::{DatabaseConnection, EntityTrait};
+
+use crate::{
+    app::error::{ApiError, ApiResult},
+    entity::{prelude::*, sys_file_upload},
+    storage::FileStorage,
+};
+
+#[derive(Debug)]
+pub struct UploadedFile {
+    pub file_name: Option<String>,
+    pub content_type: Option<String>,
+    pub data: Bytes,
+}
+
+/// Reads the multipart field named `name`, failing with `413 Payload Too
+/// Large` as soon as it grows past `limit` bytes.
+pub async fn read_file(
+    multipart: &mut Multipart,
+    name: &str,
+    limit: usize,
+) -> ApiResult<UploadedFile> {
+    while let Some(mut field) = multipart.next_field().await? {
+        if field.name() != Some(name) {
+            continue;
+        }
+
+        let file_name = field.file_name().map(str::to_string);
+        let content_type = field.content_type().map(str::to_string);
+        let mut data = Vec::new();
+        while let Some(chunk) = field.chunk().await? {
+            if data.len() + chunk.len() > limit {
+                return Err(ApiError::PayloadTooLarge(limit));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        return Ok(UploadedFile {
+            file_name,
+            content_type,
+            data: Bytes::from(data),
+        });
+    }
+
+    Err(ApiError::ValidationError(format!(
+        "{name}: missing multipart file field"
+    )))
+}
+
+/// Detects the image format from the file's magic bytes rather than the
+/// client-supplied content type.
+pub fn sniff_image(data: &[u8], allowed: &[ImageFormat]) -> Option<ImageFormat> {
+    image::guess_format(data)
+        .ok()
+        .filter(|format| allowed.contains(format))
+}
+
+/// Scales an image down to fit in a `size`x`size` box, encoded as PNG.
+pub fn thumbnail(data: &[u8], format: ImageFormat, size: u32) -> anyhow::Result<Vec<u8>> {
+    let image = image::load_from_memory_with_format(data, format)?;
+    let mut output = Cursor::new(Vec::new());
+    image
+        .resize(size, size, FilterType::Lanczos3)
+        .write_to(&mut output, ImageFormat::Png)?;
+
+    Ok(output.into_inner())
+}
+
+/// The storage key of a part of a resumable upload.
+pub fn part_key(upload_id: &str, part: i32) -> String {
+    format!("uploads/{upload_id}/{part:08}")
+}
+
+/// Deletes a resumable upload along with its stored parts.
+pub async fn remove_upload(
+    db: &DatabaseConnection,
+    storage: &dyn FileStorage,
+    upload: &sys_file_upload::Model,
+) -> ApiResult<()> {
+    SysFileUpload::delete_by_id(&upload.id)
+        .exec(db)
+        .await
+        .context("Delete the upload")?;
+
+    for part in 0..upload.parts {
+        if let Err(e) = storage.delete(&part_key(&upload.id, part)).await {
+            tracing::warn!(error = ?e, upload = upload.id, part, "Failed to delete upload part");
+        }
+    }
+
+    Ok(())
+}
@@ -5,6 +5,8 @@ use argon2::{
     password_hash::{SaltString, rand_core::OsRng},
 };
 
+use pinyin::ToPinyin;
+
 use crate::app::error::ApiResult;
 
 static FAST_ARGON2: LazyLock<Argon2> = LazyLock::new(|| {
@@ -25,3 +27,25 @@ pub fn verify_password(password: &str, hash: &str) -> ApiResult<bool> {
         .verify_password(password.as_bytes(), &parsed_hash)
         .is_ok())
 }
+
+/// Spells `text` in toneless pinyin, returning the full spelling and the
+/// initials, such as `("lisi", "ls")` for "李四". Other characters are kept in
+/// lowercase and whitespace is dropped.
+pub fn pinyin(text: &str) -> (String, String) {
+    let mut full = String::new();
+    let mut initials = String::new();
+    for (ch, pinyin) in text.chars().zip(text.to_pinyin()) {
+        match pinyin {
+            Some(pinyin) => {
+                full.push_str(pinyin.plain());
+                initials.push_str(pinyin.first_letter());
+            }
+            None if ch.is_whitespace() => {}
+            None => {
+                full.extend(ch.to_lowercase());
+                initials.extend(ch.to_lowercase());
+            }
+        }
+    }
+    (full, initials)
+}
@@ -1,8 +1,11 @@
 use std::{borrow::Cow, sync::LazyLock};
 
 use regex::Regex;
+use sea_orm::ActiveEnum;
 use validator::ValidationError;
 
+use crate::{app::patch::Patch, dict, entity::gender::Gender};
+
 static MOBILE_PHONE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^1[3-9]\d{9}$").expect("Failed to compile mobile phone regex"));
 
@@ -14,3 +17,28 @@ pub fn validate_mobile_phone(number: &str) -> Result<(), ValidationError> {
             .with_message(Cow::Borrowed("Invalid mobile phone number format")))
     }
 }
+
+pub fn validate_mobile_phone_patch(number: &Patch<String>) -> Result<(), ValidationError> {
+    number.validate_with(|number| validate_mobile_phone(number))
+}
+
+/// Checks `value` against the enabled entries of the dictionary `dict_type`.
+pub fn validate_dict(dict_type: &str, value: &str) -> Result<(), ValidationError> {
+    if dict::contains(dict_type, value) {
+        Ok(())
+    } else {
+        Err(
+            ValidationError::new("invalid_dict_value").with_message(Cow::Owned(format!(
+                "Not a value of dictionary `{dict_type}`"
+            ))),
+        )
+    }
+}
+
+pub fn validate_gender(gender: &Gender) -> Result<(), ValidationError> {
+    validate_dict(dict::GENDER, &gender.to_value())
+}
+
+pub fn validate_gender_patch(gender: &Patch<Gender>) -> Result<(), ValidationError> {
+    gender.validate_with(validate_gender)
+}
@@ -1,11 +1,15 @@
 use jsonwebtoken::Algorithm;
 use serde::Deserialize;
 
+use crate::{entity::data_scope::DataScope, setting};
+
 #[derive(Debug, Deserialize)]
 pub struct JwtConfig {
     pub secret: Option<String>,
     pub algorithm: Option<Algorithm>,
     pub expiration: Option<u64>,
+    pub default_data_scope: Option<DataScope>,
+    pub admin_accounts: Option<Vec<String>>,
 }
 
 impl JwtConfig {
@@ -18,6 +22,21 @@ impl JwtConfig {
     }
 
     pub fn expiration(&self) -> u64 {
-        self.expiration.unwrap_or(3600)
+        setting::AUTH_EXPIRATION
+            .get()
+            .unwrap_or_else(|| self.expiration.unwrap_or(3600))
+    }
+
+    /// The data scope of users without any role.
+    pub fn default_data_scope(&self) -> DataScope {
+        setting::AUTH_DEFAULT_DATA_SCOPE
+            .get()
+            .unwrap_or_else(|| self.default_data_scope.unwrap_or(DataScope::SelfOnly))
+    }
+
+    /// The accounts that see every user and hold every permission, to set up
+    /// the roles of everyone else.
+    pub fn admin_accounts(&self) -> &[String] {
+        self.admin_accounts.as_deref().unwrap_or_default()
     }
 }
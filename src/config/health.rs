@@ -0,0 +1,20 @@
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HealthConfig {
+    pub min_free_space: Option<u64>,
+    pub certificate_days: Option<i64>,
+}
+
+impl HealthConfig {
+    /// How many bytes must stay free where local storage keeps its files.
+    pub fn min_free_space(&self) -> u64 {
+        self.min_free_space.unwrap_or(512 * 1024 * 1024)
+    }
+
+    /// How many days before the TLS certificate expires the instance stops
+    /// being ready, so that it is renewed before clients notice.
+    pub fn certificate_days(&self) -> i64 {
+        self.certificate_days.unwrap_or(7)
+    }
+}
@@ -0,0 +1,26 @@
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct MetricsConfig {
+    pub enable: Option<bool>,
+    pub port: Option<u16>,
+    pub public: Option<bool>,
+}
+
+impl MetricsConfig {
+    pub fn enable(&self) -> bool {
+        self.enable.unwrap_or(true)
+    }
+
+    /// The port of the separate plain HTTP listener serving `/metrics`, to be
+    /// reachable only from an internal network.
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(9100)
+    }
+
+    /// Whether the server port serves `/metrics`, to anyone who can reach it,
+    /// instead of the separate listener.
+    pub fn public(&self) -> bool {
+        self.public.unwrap_or(false)
+    }
+}
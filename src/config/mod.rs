@@ -1,4 +1,7 @@
-use std::sync::LazyLock;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::LazyLock,
+};
 
 use anyhow::Context;
 use config::Config;
@@ -10,12 +13,76 @@ use database::DataBaseConfig;
 
 use auth::JwtConfig;
 
-use crate::config::ssl::SslConfig;
+use crate::config::{
+    health::HealthConfig, metrics::MetricsConfig, queue::QueueConfig, ssl::SslConfig,
+    storage::StorageConfig, telemetry::TelemetryConfig,
+};
 
 mod auth;
 pub mod database;
+pub mod health;
+pub mod metrics;
+pub mod queue;
 pub mod server;
 pub mod ssl;
+pub mod storage;
+pub mod telemetry;
+
+/// Environment variables named `APP_` and the path of an entry, with `__`
+/// between its parts, override the file, as in `APP_AUTH__DEFAULT_DATA_SCOPE`.
+/// Names with a single `_`, as in `APP_AUTH_SECRET`, are still honored by
+/// matching them against `KEYS`, and a variable matching no entry stops the
+/// startup rather than being ignored.
+const ENV_PREFIX: &str = "APP";
+const ENV_SEPARATOR: &str = "__";
+
+/// Every entry of the configuration.
+const KEYS: &[&str] = &[
+    "auth.secret",
+    "auth.algorithm",
+    "auth.expiration",
+    "auth.default_data_scope",
+    "auth.admin_accounts",
+    "server.port",
+    "ssl.enable",
+    "ssl.cert_path",
+    "ssl.key_path",
+    "database.type",
+    "database.host",
+    "database.port",
+    "database.username",
+    "database.password",
+    "database.database",
+    "database.schema",
+    "storage.type",
+    "storage.path",
+    "storage.max_avatar_size",
+    "storage.max_file_size",
+    "storage.s3.endpoint",
+    "storage.s3.bucket",
+    "storage.s3.region",
+    "storage.s3.access_key",
+    "storage.s3.secret_key",
+    "storage.s3.path_style",
+    "queue.workers",
+    "health.min_free_space",
+    "health.certificate_days",
+    "metrics.enable",
+    "metrics.port",
+    "metrics.public",
+    "telemetry.enable",
+    "telemetry.protocol",
+    "telemetry.endpoint",
+    "telemetry.service_name",
+    "telemetry.sample_ratio",
+];
+
+/// The entries set by the environment of the process.
+static ENV_KEYS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
+    env_vars()
+        .filter_map(|(var, _)| env_key(&var)?.ok())
+        .collect()
+});
 
 static CONFIG: LazyLock<AppConfig> =
     LazyLock::new(|| AppConfig::load().expect("Failed to load configuration"));
@@ -26,6 +93,17 @@ pub struct AppConfig {
     ssl: SslConfig,
     server: ServerConfig,
     database: DataBaseConfig,
+    // The sections below may be left out for their defaults.
+    #[serde(default)]
+    storage: StorageConfig,
+    #[serde(default)]
+    queue: QueueConfig,
+    #[serde(default)]
+    health: HealthConfig,
+    #[serde(default)]
+    metrics: MetricsConfig,
+    #[serde(default)]
+    telemetry: TelemetryConfig,
 }
 
 impl AppConfig {
@@ -36,12 +114,7 @@ impl AppConfig {
                     .format(config::FileFormat::Toml)
                     .required(true),
             )
-            .add_source(
-                config::Environment::with_prefix("APP")
-                    .try_parsing(true)
-                    .separator("_")
-                    .list_separator(","),
-            )
+            .add_source(environment().source(Some(env_overrides(env_vars())?)))
             .build()
             .context("Build the configuration")?
             .try_deserialize()
@@ -63,8 +136,182 @@ impl AppConfig {
     pub fn database(&self) -> &DataBaseConfig {
         &self.database
     }
+
+    pub fn storage(&self) -> &StorageConfig {
+        &self.storage
+    }
+
+    pub fn queue(&self) -> &QueueConfig {
+        &self.queue
+    }
+
+    pub fn health(&self) -> &HealthConfig {
+        &self.health
+    }
+
+    pub fn metrics(&self) -> &MetricsConfig {
+        &self.metrics
+    }
+
+    pub fn telemetry(&self) -> &TelemetryConfig {
+        &self.telemetry
+    }
 }
 
 pub fn get() -> &'static AppConfig {
     &CONFIG
 }
+
+fn environment() -> config::Environment {
+    config::Environment::with_prefix(ENV_PREFIX)
+        .prefix_separator("_")
+        .separator(ENV_SEPARATOR)
+        .try_parsing(true)
+        .list_separator(",")
+        // Without naming them, every value would be parsed as a list.
+        .with_list_parse_key("auth.admin_accounts")
+}
+
+/// The environment variable overriding the entry at `key`, such as
+/// `auth.default_data_scope`.
+pub fn env_var(key: &str) -> String {
+    format!(
+        "{ENV_PREFIX}_{}",
+        key.replace('.', ENV_SEPARATOR).to_uppercase()
+    )
+}
+
+/// Whether the environment sets the entry at `key`, under either name.
+pub fn is_env_set(key: &str) -> bool {
+    ENV_KEYS.contains(key)
+}
+
+fn env_vars() -> impl Iterator<Item = (String, String)> {
+    std::env::vars_os()
+        .filter_map(|(var, value)| Some((var.into_string().ok()?, value.into_string().ok()?)))
+}
+
+/// The entry `var` overrides, or `None` if it is not an `APP_` variable.
+fn env_key(var: &str) -> Option<anyhow::Result<&'static str>> {
+    let name = var
+        .strip_prefix(ENV_PREFIX)?
+        .strip_prefix('_')?
+        .to_lowercase();
+    let key = KEYS
+        .iter()
+        .copied()
+        .find(|key| key.replace('.', ENV_SEPARATOR) == name || key.replace('.', "_") == name)
+        .with_context(|| format!("`{var}` does not name a configuration entry"));
+    Some(key)
+}
+
+/// The `APP_` variables among `vars`, renamed to the `__` form that
+/// `environment` reads.
+fn env_overrides(
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> anyhow::Result<HashMap<String, String>> {
+    let mut overrides = HashMap::new();
+    for (var, value) in vars {
+        if let Some(key) = env_key(&var) {
+            overrides.insert(env_var(key?), value);
+        }
+    }
+    Ok(overrides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::storage::StorageType;
+
+    fn from_env<const N: usize>(vars: [(String, &str); N]) -> anyhow::Result<Config> {
+        let vars = vars.map(|(var, value)| (var, value.to_string()));
+        Ok(Config::builder()
+            .add_source(environment().source(Some(env_overrides(vars)?)))
+            .build()?)
+    }
+
+    #[test]
+    fn env_var_names_the_entry_it_overrides() {
+        assert_eq!(
+            env_var("auth.default_data_scope"),
+            "APP_AUTH__DEFAULT_DATA_SCOPE"
+        );
+
+        let config = from_env([
+            (env_var("auth.default_data_scope"), "all"),
+            (env_var("server.port"), "8080"),
+            (env_var("auth.admin_accounts"), "admin,ops"),
+        ])
+        .unwrap();
+        assert_eq!(config.get_string("auth.default_data_scope").unwrap(), "all");
+        assert_eq!(config.get_int("server.port").unwrap(), 8080);
+        assert_eq!(
+            config.get::<Vec<String>>("auth.admin_accounts").unwrap(),
+            ["admin", "ops"]
+        );
+    }
+
+    #[test]
+    fn optional_sections_default() {
+        let file = include_str!("../../application.toml");
+        let required = &file[..file.find("[storage]").unwrap()];
+        let config: AppConfig = Config::builder()
+            .add_source(config::File::from_str(required, config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        assert!(matches!(config.storage().get_type(), StorageType::Local));
+        assert_eq!(config.storage().max_file_size(), 100 * 1024 * 1024);
+        assert_eq!(config.queue().workers(), 4);
+        assert_eq!(config.health().certificate_days(), 7);
+        assert!(config.metrics().enable());
+        assert_eq!(config.metrics().port(), 9100);
+        assert!(!config.metrics().public());
+        assert!(!config.telemetry().enable());
+    }
+
+    #[test]
+    fn applies_single_underscore_names() {
+        let config = from_env([
+            ("APP_AUTH_SECRET".to_string(), "from-env"),
+            ("APP_SERVER_PORT".to_string(), "8080"),
+            ("APP_AUTH_DEFAULT_DATA_SCOPE".to_string(), "all"),
+            ("APP_STORAGE_S3_ACCESS_KEY".to_string(), "key"),
+            ("OTHER_SECRET".to_string(), "ignored"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.get_string("auth.secret").unwrap(), "from-env");
+        assert_eq!(config.get_int("server.port").unwrap(), 8080);
+        assert_eq!(config.get_string("auth.default_data_scope").unwrap(), "all");
+        assert_eq!(config.get_string("storage.s3.access_key").unwrap(), "key");
+    }
+
+    #[test]
+    fn rejects_variables_naming_no_entry() {
+        let error = from_env([("APP_AUTH_SECERT".to_string(), "typo")]).unwrap_err();
+        assert!(error.to_string().contains("APP_AUTH_SECERT"));
+        assert!(from_env([("APP_SERVER__PROT".to_string(), "8080")]).is_err());
+    }
+
+    #[test]
+    fn keys_cover_the_file() {
+        let file = include_str!("../../application.toml");
+        let config = Config::builder()
+            .add_source(config::File::from_str(file, config::FileFormat::Toml))
+            .build()
+            .unwrap();
+        let sections = config
+            .try_deserialize::<HashMap<String, HashMap<String, config::Value>>>()
+            .unwrap();
+        for (section, entries) in sections {
+            for entry in entries.keys() {
+                let key = format!("{section}.{entry}");
+                assert!(KEYS.contains(&key.as_str()), "`{key}` is missing from KEYS");
+            }
+        }
+    }
+}
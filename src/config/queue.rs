@@ -0,0 +1,13 @@
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct QueueConfig {
+    pub workers: Option<usize>,
+}
+
+impl QueueConfig {
+    /// How many tasks this instance runs at once.
+    pub fn workers(&self) -> usize {
+        self.workers.unwrap_or(4).max(1)
+    }
+}
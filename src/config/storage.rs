@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::setting;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub r#type: StorageType,
+    pub path: Option<PathBuf>,
+    pub max_avatar_size: Option<usize>,
+    pub max_file_size: Option<u64>,
+    pub s3: Option<S3Config>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub enum StorageType {
+    #[default]
+    Local,
+    S3,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: Option<String>,
+    pub access_key: String,
+    pub secret_key: String,
+    pub path_style: Option<bool>,
+}
+
+impl StorageConfig {
+    pub fn get_type(&self) -> &StorageType {
+        &self.r#type
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_deref().unwrap_or(Path::new("./uploads"))
+    }
+
+    pub fn max_avatar_size(&self) -> usize {
+        setting::STORAGE_MAX_AVATAR_SIZE
+            .get()
+            .unwrap_or_else(|| self.max_avatar_size.unwrap_or(2 * 1024 * 1024))
+    }
+
+    pub fn max_file_size(&self) -> u64 {
+        setting::STORAGE_MAX_FILE_SIZE
+            .get()
+            .unwrap_or_else(|| self.max_file_size.unwrap_or(100 * 1024 * 1024))
+    }
+
+    pub fn s3(&self) -> Option<&S3Config> {
+        self.s3.as_ref()
+    }
+}
+
+impl S3Config {
+    pub fn region(&self) -> &str {
+        self.region.as_deref().unwrap_or("us-east-1")
+    }
+
+    /// Whether the bucket goes in the path rather than the host name, which
+    /// is what most self-hosted S3-compatible servers expect.
+    pub fn path_style(&self) -> bool {
+        self.path_style.unwrap_or(true)
+    }
+}
@@ -0,0 +1,46 @@
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TelemetryConfig {
+    #[serde(default)]
+    pub enable: bool,
+    pub protocol: Option<OtlpProtocol>,
+    pub endpoint: Option<String>,
+    pub service_name: Option<String>,
+    pub sample_ratio: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum OtlpProtocol {
+    Grpc,
+    Http,
+}
+
+impl TelemetryConfig {
+    pub fn enable(&self) -> bool {
+        self.enable
+    }
+
+    pub fn protocol(&self) -> OtlpProtocol {
+        self.protocol.unwrap_or(OtlpProtocol::Grpc)
+    }
+
+    /// Where the collector listens, defaulting to its standard port for the
+    /// protocol. The HTTP endpoint is the full URL, ending in `/v1/traces`.
+    pub fn endpoint(&self) -> &str {
+        self.endpoint.as_deref().unwrap_or(match self.protocol() {
+            OtlpProtocol::Grpc => "http://localhost:4317",
+            OtlpProtocol::Http => "http://localhost:4318/v1/traces",
+        })
+    }
+
+    pub fn service_name(&self) -> &str {
+        self.service_name.as_deref().unwrap_or("rust-web")
+    }
+
+    /// The share of traces started here that are exported, from 0 to 1.
+    /// Requests carrying a `traceparent` follow the caller's decision.
+    pub fn sample_ratio(&self) -> f64 {
+        self.sample_ratio.unwrap_or(1.0).clamp(0.0, 1.0)
+    }
+}
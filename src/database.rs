@@ -1,9 +1,21 @@
-use std::time::Duration;
+use std::{collections::HashSet, sync::Arc, time::Duration};
 
 use anyhow::Context;
-use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use async_trait::async_trait;
+use sea_orm::{
+    ColumnTrait, ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend,
+    EntityTrait, IdenStatic, JsonValue, QueryFilter, Schema, Statement, prelude::Expr,
+    sea_query::Table,
+};
+use serde_json::json;
 
-use crate::config::{self, database::DatabaseType};
+use crate::{
+    app::{AppState, util},
+    config::{self, database::DatabaseType},
+    entity::{prelude::*, sys_job_run, sys_user},
+    health::{self, Check},
+    logger, search,
+};
 
 pub async fn init() -> anyhow::Result<DatabaseConnection> {
     let config = config::get().database();
@@ -28,7 +40,181 @@ pub async fn init() -> anyhow::Result<DatabaseConnection> {
         .sqlx_logging(false)
         .set_schema_search_path(config.schema());
 
-    Database::connect(option)
+    let mut db = Database::connect(option)
         .await
-        .context("Connect to the database")
+        .context("Connect to the database")?;
+    if config::get().telemetry().enable() {
+        db.set_metric_callback(logger::record_query);
+    }
+
+    sync_schema(&db).await?;
+    health::register(Arc::new(Ping));
+
+    Ok(db)
+}
+
+/// Pings the database through the connection pool.
+struct Ping;
+
+#[async_trait]
+impl Check for Ping {
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    async fn check(&self, state: &AppState) -> anyhow::Result<JsonValue> {
+        state.db.ping().await.context("Ping the database")?;
+        Ok(json!({ "backend": format!("{:?}", state.db.get_database_backend()) }))
+    }
+}
+
+/// Creates the tables, nullable columns and search indexes introduced after
+/// `sys_user` when they are missing.
+async fn sync_schema(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let schema = Schema::new(db.get_database_backend());
+
+    add_columns(
+        db,
+        &schema,
+        SysUser,
+        [
+            sys_user::Column::Avatar,
+            sys_user::Column::NamePinyin,
+            sys_user::Column::NameInitials,
+            sys_user::Column::DeptId,
+        ],
+    )
+    .await?;
+    fill_name_pinyin(db).await?;
+    create_table(db, &schema, SysUserHistory).await?;
+    create_table(db, &schema, SysFile).await?;
+    create_table(db, &schema, SysFileUpload).await?;
+    create_table(db, &schema, SysDept).await?;
+    create_table(db, &schema, SysRole).await?;
+    create_table(db, &schema, SysUserRole).await?;
+    create_table(db, &schema, SysDictType).await?;
+    create_table(db, &schema, SysDictData).await?;
+    create_table(db, &schema, SysConfig).await?;
+    create_table(db, &schema, SysMenu).await?;
+    create_table(db, &schema, SysRoleMenu).await?;
+    create_table(db, &schema, SysNotification).await?;
+    create_table(db, &schema, SysJob).await?;
+    create_table(db, &schema, SysJobRun).await?;
+    add_columns(db, &schema, SysJobRun, [sys_job_run::Column::LockedUntil]).await?;
+    create_table(db, &schema, SysTask).await?;
+    create_table(db, &schema, SysWebhook).await?;
+    create_table(db, &schema, SysWebhookDelivery).await?;
+    search::SYS_USER.sync(db).await?;
+
+    Ok(())
+}
+
+async fn add_columns<E: EntityTrait>(
+    db: &DatabaseConnection,
+    schema: &Schema,
+    entity: E,
+    columns: impl IntoIterator<Item = E::Column>,
+) -> anyhow::Result<()> {
+    let backend = db.get_database_backend();
+    let existing = existing_columns(db, entity.table_name()).await?;
+
+    for column in columns {
+        if existing.contains(column.as_str()) {
+            continue;
+        }
+        let statement = Table::alter()
+            .table(entity)
+            .add_column(schema.get_column_def::<E>(column))
+            .to_owned();
+        db.execute(backend.build(&statement))
+            .await
+            .with_context(|| format!("Add column {}.{}", entity.table_name(), column.as_str()))?;
+    }
+
+    Ok(())
+}
+
+pub async fn existing_columns(
+    db: &DatabaseConnection,
+    table: &str,
+) -> anyhow::Result<HashSet<String>> {
+    let backend = db.get_database_backend();
+    let sql = match backend {
+        DbBackend::Sqlite => "SELECT name FROM pragma_table_info(?)",
+        _ => {
+            "SELECT column_name AS name FROM information_schema.columns \
+             WHERE table_schema = current_schema() AND table_name = $1"
+        }
+    };
+
+    db.query_all(Statement::from_sql_and_values(backend, sql, [table.into()]))
+        .await
+        .with_context(|| format!("List columns of {table}"))?
+        .iter()
+        .map(|row| row.try_get::<String>("", "name").map_err(Into::into))
+        .collect()
+}
+
+/// Spells the names of users saved before the pinyin columns existed.
+async fn fill_name_pinyin(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let users = SysUser::find()
+        .filter(sys_user::Column::NamePinyin.is_null())
+        .all(db)
+        .await
+        .context("Find users without name pinyin")?;
+
+    for user in users {
+        let (pinyin, initials) = util::pinyin(&user.name);
+        SysUser::update_many()
+            .col_expr(sys_user::Column::NamePinyin, Expr::value(pinyin))
+            .col_expr(sys_user::Column::NameInitials, Expr::value(initials))
+            .filter(sys_user::Column::Id.eq(user.id))
+            .exec(db)
+            .await
+            .context("Fill name pinyin")?;
+    }
+
+    Ok(())
+}
+
+async fn create_table<E: EntityTrait>(
+    db: &DatabaseConnection,
+    schema: &Schema,
+    entity: E,
+) -> anyhow::Result<()> {
+    let backend = db.get_database_backend();
+
+    db.execute(backend.build(schema.create_table_from_entity(entity).if_not_exists()))
+        .await
+        .with_context(|| format!("Create table {}", entity.table_name()))?;
+
+    for mut index in schema.create_index_from_entity(entity) {
+        db.execute(backend.build(index.if_not_exists()))
+            .await
+            .with_context(|| format!("Create indexes of {}", entity.table_name()))?;
+    }
+
+    Ok(())
+}
+
+/// A fresh in-memory SQLite database with the whole schema, for tests. It
+/// keeps to a single connection, which holds the database.
+#[cfg(test)]
+pub async fn memory() -> DatabaseConnection {
+    let mut option = ConnectOptions::new("sqlite::memory:");
+    option
+        .max_connections(1)
+        .min_connections(1)
+        .sqlx_logging(false);
+    let db = Database::connect(option)
+        .await
+        .expect("Open an in-memory database");
+
+    let schema = Schema::new(db.get_database_backend());
+    create_table(&db, &schema, SysUser)
+        .await
+        .expect("Create sys_user");
+    sync_schema(&db).await.expect("Create the schema");
+
+    db
 }
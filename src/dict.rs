@@ -0,0 +1,135 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use anyhow::Context;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, Iterable,
+    QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::Serialize;
+
+use crate::{
+    entity::{gender::Gender, prelude::*, sys_dict_data, sys_dict_type},
+    push,
+};
+
+/// The dictionary of `Gender`, which validation of users relies on.
+pub const GENDER: &str = "gender";
+
+/// The dictionaries seeded from enums, whose types and entries cannot be
+/// deleted.
+pub const BUILT_IN: &[&str] = &[GENDER];
+
+/// An enabled entry of a dictionary, as the front end renders it.
+#[derive(Debug, Clone, Serialize)]
+pub struct DictItem {
+    pub value: String,
+    pub label: String,
+}
+
+/// The enabled entries of every dictionary type by code. It is loaded at
+/// startup and reloaded after each write through the API, so lookups never
+/// touch the database.
+static DICTS: LazyLock<RwLock<HashMap<String, Arc<[DictItem]>>>> = LazyLock::new(Default::default);
+
+pub async fn init(db: &DatabaseConnection) -> anyhow::Result<()> {
+    seed_gender(db).await?;
+    reload(db).await
+}
+
+pub async fn reload(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let types = SysDictType::find()
+        .all(db)
+        .await
+        .context("Load dictionary types")?;
+    let data = SysDictData::find()
+        .filter(sys_dict_data::Column::Enabled.eq(true))
+        .order_by_asc(sys_dict_data::Column::Sort)
+        .order_by_asc(sys_dict_data::Column::Value)
+        .all(db)
+        .await
+        .context("Load dictionary data")?;
+
+    let mut items = types
+        .into_iter()
+        .map(|dict_type| (dict_type.code, Vec::new()))
+        .collect::<HashMap<_, _>>();
+    for data in data {
+        if let Some(entries) = items.get_mut(&data.dict_type) {
+            entries.push(DictItem {
+                value: data.value,
+                label: data.label,
+            });
+        }
+    }
+
+    let dicts = items
+        .into_iter()
+        .map(|(code, entries)| (code, Arc::from(entries)))
+        .collect();
+    *DICTS.write().expect("Dictionary cache lock poisoned") = dicts;
+    // Lets the front end refetch the dictionaries it cached.
+    push::broadcast("dicts", ());
+
+    Ok(())
+}
+
+/// The enabled entries of a dictionary type, or `None` if it does not exist.
+pub fn items(dict_type: &str) -> Option<Arc<[DictItem]>> {
+    DICTS
+        .read()
+        .expect("Dictionary cache lock poisoned")
+        .get(dict_type)
+        .cloned()
+}
+
+pub fn contains(dict_type: &str, value: &str) -> bool {
+    items(dict_type).is_some_and(|items| items.iter().any(|item| item.value == value))
+}
+
+/// Creates the `gender` dictionary from `Gender` on first start, giving the
+/// front end labels for it.
+async fn seed_gender(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let exists = SysDictType::find()
+        .filter(sys_dict_type::Column::Code.eq(GENDER))
+        .one(db)
+        .await
+        .context("Find the gender dictionary")?
+        .is_some();
+    if exists {
+        return Ok(());
+    }
+
+    let txn = db.begin().await?;
+    sys_dict_type::ActiveModel {
+        code: ActiveValue::Set(GENDER.to_string()),
+        name: ActiveValue::Set("性别".to_string()),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .context("Create the gender dictionary")?;
+
+    for (sort, gender) in Gender::iter().enumerate() {
+        let label = match gender {
+            Gender::Male => "男",
+            Gender::Female => "女",
+        };
+        sys_dict_data::ActiveModel {
+            dict_type: ActiveValue::Set(GENDER.to_string()),
+            value: ActiveValue::Set(sea_orm::ActiveEnum::to_value(&gender)),
+            label: ActiveValue::Set(label.to_string()),
+            sort: ActiveValue::Set(sort as i32),
+            enabled: ActiveValue::Set(true),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .context("Create the gender dictionary data")?;
+    }
+    txn.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use sea_orm::{ActiveValue, IntoActiveValue, prelude::*};
+use serde::{Deserialize, Serialize};
+
+/// The users a role may see, ordered from the narrowest to the widest.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    EnumIter,
+    DeriveActiveEnum,
+)]
+#[serde(rename_all = "camelCase")]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "String(StringLen::N(32))",
+    rename_all = "snake_case"
+)]
+pub enum DataScope {
+    /// Only the user themselves.
+    SelfOnly,
+    /// Users of the user's own department.
+    Dept,
+    /// Users of the user's department and its descendants.
+    DeptAndChildren,
+    /// Every user.
+    All,
+}
+
+impl IntoActiveValue<DataScope> for DataScope {
+    fn into_active_value(self) -> ActiveValue<DataScope> {
+        ActiveValue::Set(self)
+    }
+}
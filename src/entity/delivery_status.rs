@@ -0,0 +1,18 @@
+use sea_orm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The state of a `sys_webhook_delivery`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "camelCase")]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "String(StringLen::N(16))",
+    rename_all = "snake_case"
+)]
+pub enum DeliveryStatus {
+    /// Not attempted yet.
+    Pending,
+    Succeeded,
+    /// The last attempt failed. The delivery task retries it with backoff.
+    Failed,
+}
@@ -0,0 +1,31 @@
+use sea_orm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The state of a `sys_job_run`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "camelCase")]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "String(StringLen::N(16))",
+    rename_all = "snake_case"
+)]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// What started a `sys_job_run`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "camelCase")]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "String(StringLen::N(16))",
+    rename_all = "snake_case"
+)]
+pub enum JobTrigger {
+    /// The job's cron expression.
+    Schedule,
+    /// An administrator, through the API.
+    Manual,
+}
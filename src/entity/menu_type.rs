@@ -0,0 +1,25 @@
+use sea_orm::{ActiveValue, IntoActiveValue, prelude::*};
+use serde::{Deserialize, Serialize};
+
+/// The kind of a `sys_menu` entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "camelCase")]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "String(StringLen::N(16))",
+    rename_all = "snake_case"
+)]
+pub enum MenuType {
+    /// A group of pages in the navigation.
+    Directory,
+    /// A routed page of the front end.
+    Page,
+    /// An action within a page, only granting its permission key.
+    Button,
+}
+
+impl IntoActiveValue<MenuType> for MenuType {
+    fn into_active_value(self) -> ActiveValue<MenuType> {
+        ActiveValue::Set(self)
+    }
+}
@@ -2,6 +2,28 @@
 
 pub mod prelude;
 
+pub mod sys_config;
+pub mod sys_dept;
+pub mod sys_dict_data;
+pub mod sys_dict_type;
+pub mod sys_file;
+pub mod sys_file_upload;
+pub mod sys_job;
+pub mod sys_job_run;
+pub mod sys_menu;
+pub mod sys_notification;
+pub mod sys_role;
+pub mod sys_role_menu;
+pub mod sys_task;
 pub mod sys_user;
+pub mod sys_user_history;
+pub mod sys_user_role;
+pub mod sys_webhook;
+pub mod sys_webhook_delivery;
 
+pub mod data_scope;
+pub mod delivery_status;
 pub mod gender;
+pub mod job_status;
+pub mod menu_type;
+pub mod task_status;
@@ -1,3 +1,20 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.14
 
+pub use super::sys_config::Entity as SysConfig;
+pub use super::sys_dept::Entity as SysDept;
+pub use super::sys_dict_data::Entity as SysDictData;
+pub use super::sys_dict_type::Entity as SysDictType;
+pub use super::sys_file::Entity as SysFile;
+pub use super::sys_file_upload::Entity as SysFileUpload;
+pub use super::sys_job::Entity as SysJob;
+pub use super::sys_job_run::Entity as SysJobRun;
+pub use super::sys_menu::Entity as SysMenu;
+pub use super::sys_notification::Entity as SysNotification;
+pub use super::sys_role::Entity as SysRole;
+pub use super::sys_role_menu::Entity as SysRoleMenu;
+pub use super::sys_task::Entity as SysTask;
 pub use super::sys_user::Entity as SysUser;
+pub use super::sys_user_history::Entity as SysUserHistory;
+pub use super::sys_user_role::Entity as SysUserRole;
+pub use super::sys_webhook::Entity as SysWebhook;
+pub use super::sys_webhook_delivery::Entity as SysWebhookDelivery;
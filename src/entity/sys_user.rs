@@ -3,7 +3,7 @@
 use sea_orm::{ActiveValue, entity::prelude::*, prelude::async_trait::async_trait};
 use serde::{Deserialize, Serialize};
 
-use crate::entity::gender::Gender;
+use crate::{app::util, entity::gender::Gender};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "sys_user")]
@@ -14,11 +14,18 @@ pub struct Model {
     pub name: String,
     pub gender: Gender,
     pub account: String,
-    #[serde(skip_serializing)]
+    /// Never serialized, so it is empty in a user read back from JSON.
+    #[serde(default, skip_serializing)]
     pub password: String,
     pub mobile_phone: String,
     pub birthday: Date,
     pub enabled: bool,
+    pub avatar: Option<String>,
+    pub dept_id: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub name_pinyin: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub name_initials: Option<String>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
@@ -34,6 +41,13 @@ impl ActiveModelBehavior for ActiveModel {
     {
         if insert {
             self.id = ActiveValue::Set(Uuid::now_v7().simple().to_string());
+        } else {
+            self.updated_at = ActiveValue::Set(chrono::Utc::now().naive_utc());
+        }
+        if let ActiveValue::Set(name) = &self.name {
+            let (pinyin, initials) = util::pinyin(name);
+            self.name_pinyin = ActiveValue::Set(Some(pinyin));
+            self.name_initials = ActiveValue::Set(Some(initials));
         }
         Ok(self)
     }
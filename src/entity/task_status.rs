@@ -0,0 +1,20 @@
+use sea_orm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The state of a `sys_task`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "camelCase")]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "String(StringLen::N(16))",
+    rename_all = "snake_case"
+)]
+pub enum TaskStatus {
+    /// Waiting for a worker, possibly until `run_at` after a failed attempt.
+    Pending,
+    /// Claimed by a worker until `locked_until`.
+    Running,
+    Succeeded,
+    /// Failed every attempt. Only an administrator retries it.
+    Dead,
+}
@@ -0,0 +1,250 @@
+use std::{any::Any, collections::HashMap, marker::PhantomData, sync::Arc};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use sea_orm::{ConnectionTrait, JsonValue};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tracing::error;
+
+use crate::{
+    app::AppState,
+    entity::sys_user,
+    push,
+    queue::{
+        self,
+        event::{Handle, Handling},
+    },
+    webhook,
+};
+
+/// Something that happened, published to the subscribers of its type.
+pub trait Event: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// The unique name of the event, which webhooks also subscribe to.
+    const NAME: &'static str;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UserCreated(pub sys_user::Model);
+
+impl Event for UserCreated {
+    const NAME: &'static str = "user.created";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UserUpdated(pub sys_user::Model);
+
+impl Event for UserUpdated {
+    const NAME: &'static str = "user.updated";
+}
+
+/// Published along with `UserUpdated` when a user is disabled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UserDisabled(pub sys_user::Model);
+
+impl Event for UserDisabled {
+    const NAME: &'static str = "user.disabled";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UserDeleted(pub sys_user::Model);
+
+impl Event for UserDeleted {
+    const NAME: &'static str = "user.deleted";
+}
+
+/// Reacts to events of type `E`.
+#[async_trait]
+pub trait Subscriber<E: Event>: Send + Sync + 'static {
+    /// The name of the subscriber, unique among those of `E`.
+    fn name(&self) -> &'static str;
+
+    /// Whether events reach the subscriber through the transactional
+    /// outbox: queued in the publishing transaction and retried until
+    /// handled, so it must tolerate handling an event more than once.
+    /// Otherwise they are handled in-process once the transaction commits,
+    /// and lost if that fails.
+    fn durable(&self) -> bool {
+        false
+    }
+
+    async fn handle(&self, state: &AppState, event: &E) -> anyhow::Result<()>;
+}
+
+/// A subscriber with its event type erased, so the subscribers of all events
+/// fit in one map.
+#[async_trait]
+trait Subscription: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn durable(&self) -> bool;
+
+    async fn handle(&self, state: &AppState, event: &(dyn Any + Send + Sync))
+    -> anyhow::Result<()>;
+
+    async fn handle_json(&self, state: &AppState, event: JsonValue) -> anyhow::Result<()>;
+}
+
+struct Typed<E, S> {
+    subscriber: S,
+    event: PhantomData<fn(E)>,
+}
+
+#[async_trait]
+impl<E: Event, S: Subscriber<E>> Subscription for Typed<E, S> {
+    fn name(&self) -> &'static str {
+        self.subscriber.name()
+    }
+
+    fn durable(&self) -> bool {
+        self.subscriber.durable()
+    }
+
+    async fn handle(
+        &self,
+        state: &AppState,
+        event: &(dyn Any + Send + Sync),
+    ) -> anyhow::Result<()> {
+        let event = event
+            .downcast_ref::<E>()
+            .context("The event does not match its name")?;
+        self.subscriber.handle(state, event).await
+    }
+
+    async fn handle_json(&self, state: &AppState, event: JsonValue) -> anyhow::Result<()> {
+        let event = serde_json::from_value::<E>(event)
+            .with_context(|| format!("Parse the `{}` event", E::NAME))?;
+        self.subscriber.handle(state, &event).await
+    }
+}
+
+/// Delivers events to the subscribers registered at startup.
+#[derive(Default)]
+pub struct EventBus {
+    subscriptions: HashMap<&'static str, Vec<Arc<dyn Subscription>>>,
+}
+
+impl EventBus {
+    pub fn subscribe<E: Event>(&mut self, subscriber: impl Subscriber<E>) -> &mut Self {
+        self.subscriptions
+            .entry(E::NAME)
+            .or_default()
+            .push(Arc::new(Typed {
+                subscriber,
+                event: PhantomData,
+            }));
+        self
+    }
+
+    fn subscriptions(&self, event: &str) -> &[Arc<dyn Subscription>] {
+        self.subscriptions.get(event).map_or(&[], Vec::as_slice)
+    }
+
+    /// Starts collecting the events of a transaction.
+    pub fn batch(&self) -> EventBatch<'_> {
+        EventBatch {
+            bus: self,
+            events: Vec::new(),
+        }
+    }
+
+    /// Runs the subscriber named `subscriber` of an event from the outbox.
+    pub async fn handle_queued(
+        &self,
+        state: &AppState,
+        event: &str,
+        subscriber: &str,
+        payload: JsonValue,
+    ) -> anyhow::Result<()> {
+        let subscription = self
+            .subscriptions(event)
+            .iter()
+            .find(|subscription| subscription.name() == subscriber)
+            .with_context(|| format!("`{subscriber}` no longer subscribes to `{event}`"))?;
+        subscription.handle_json(state, payload).await
+    }
+}
+
+/// The events published in a transaction. Call `deliver` once it commits,
+/// for the subscribers that handle them in-process.
+pub struct EventBatch<'a> {
+    bus: &'a EventBus,
+    events: Vec<(&'static str, Box<dyn Any + Send + Sync>)>,
+}
+
+impl EventBatch<'_> {
+    /// Queues `event` in `txn` for its durable subscribers, and keeps it for
+    /// the others until `deliver`.
+    pub async fn publish<E: Event, C: ConnectionTrait>(
+        &mut self,
+        txn: &C,
+        event: E,
+    ) -> anyhow::Result<()> {
+        let subscriptions = self.bus.subscriptions(E::NAME);
+
+        if subscriptions
+            .iter()
+            .any(|subscription| subscription.durable())
+        {
+            let payload = serde_json::to_value(&event).context("Serialize the event")?;
+            for subscription in subscriptions
+                .iter()
+                .filter(|subscription| subscription.durable())
+            {
+                queue::enqueue(
+                    txn,
+                    &Handle,
+                    Handling {
+                        event: E::NAME.to_string(),
+                        subscriber: subscription.name().to_string(),
+                        payload: payload.clone(),
+                    },
+                )
+                .await?;
+            }
+        }
+        if subscriptions
+            .iter()
+            .any(|subscription| !subscription.durable())
+        {
+            self.events.push((E::NAME, Box::new(event)));
+        }
+
+        Ok(())
+    }
+
+    /// Runs the in-process subscribers of the published events, after the
+    /// transaction committed. A failing subscriber is logged and does not
+    /// stop the others.
+    pub async fn deliver(self, state: &AppState) {
+        for (name, event) in &self.events {
+            for subscription in self.bus.subscriptions(name) {
+                if subscription.durable() {
+                    continue;
+                }
+                if let Err(e) = subscription.handle(state, event.as_ref()).await {
+                    error!(
+                        event = name,
+                        subscriber = subscription.name(),
+                        error = ?e,
+                        "Event subscriber failed"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The bus with every subscriber registered.
+pub fn bus() -> EventBus {
+    let mut bus = EventBus::default();
+    bus.subscribe::<UserCreated>(webhook::Forward)
+        .subscribe::<UserUpdated>(webhook::Forward)
+        .subscribe::<UserDisabled>(webhook::Forward)
+        .subscribe::<UserDeleted>(webhook::Forward)
+        .subscribe::<UserUpdated>(push::RefreshProfile);
+    bus
+}
@@ -0,0 +1,107 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, LazyLock, RwLock},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use sea_orm::JsonValue;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::app::AppState;
+
+/// How long a check may take before it counts as failed, well within the
+/// timeouts orchestrators give readiness probes.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The checks registered by the modules this instance started.
+static CHECKS: LazyLock<RwLock<Vec<Arc<dyn Check>>>> = LazyLock::new(RwLock::default);
+
+/// Something this instance needs to serve requests, probed for readiness.
+#[async_trait]
+pub trait Check: Send + Sync + 'static {
+    /// The unique name of the check in the readiness report.
+    fn name(&self) -> &'static str;
+
+    /// Returns details for the report, or an error if the instance should not
+    /// receive traffic.
+    async fn check(&self, state: &AppState) -> anyhow::Result<JsonValue>;
+}
+
+/// Adds a check to the readiness report, replacing any check of the same
+/// name.
+pub fn register(check: Arc<dyn Check>) {
+    let mut checks = CHECKS.write().expect("Health checks lock poisoned");
+    checks.retain(|registered| registered.name() != check.name());
+    checks.push(check);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckReport {
+    pub status: Status,
+    pub duration_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The outcome of every check. The instance is up only if all of them are.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub status: Status,
+    pub checks: BTreeMap<&'static str, CheckReport>,
+}
+
+/// Runs every check, each bounded by `CHECK_TIMEOUT`.
+pub async fn check(state: &AppState) -> Report {
+    let registered = CHECKS.read().expect("Health checks lock poisoned").clone();
+    let mut checks = BTreeMap::new();
+
+    for check in registered {
+        let started = Instant::now();
+        let result = match tokio::time::timeout(CHECK_TIMEOUT, check.check(state)).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow::anyhow!("Timed out after {CHECK_TIMEOUT:?}")),
+        };
+        let duration_ms = started.elapsed().as_millis();
+
+        let report = match result {
+            Ok(details) => CheckReport {
+                status: Status::Up,
+                duration_ms,
+                details: Some(details),
+                error: None,
+            },
+            Err(e) => {
+                // Probes repeat every few seconds, so skip the backtrace.
+                let error = format!("{e:#}");
+                warn!(check = check.name(), error, "Health check failed");
+                CheckReport {
+                    status: Status::Down,
+                    duration_ms,
+                    details: None,
+                    error: Some(error),
+                }
+            }
+        };
+        checks.insert(check.name(), report);
+    }
+
+    let status = if checks.values().all(|check| check.status == Status::Up) {
+        Status::Up
+    } else {
+        Status::Down
+    };
+
+    Report { status, checks }
+}
@@ -0,0 +1,440 @@
+use std::{
+    collections::{HashMap, HashSet, hash_map::Entry},
+    str::FromStr,
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{Local, NaiveDateTime, Utc};
+use cron::Schedule;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, DatabaseConnection, EntityTrait,
+    IntoActiveModel, QueryFilter, QueryOrder, sea_query::Expr,
+};
+use tokio::{
+    sync::Notify,
+    task::{AbortHandle, JoinSet},
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    app::AppState,
+    entity::{
+        job_status::{JobStatus, JobTrigger},
+        prelude::*,
+        sys_job, sys_job_run,
+    },
+};
+
+mod purge;
+
+/// The longest the scheduler sleeps before checking the jobs again, bounding
+/// how late it notices jobs changed by another instance.
+const MAX_IDLE: Duration = Duration::from_secs(60);
+
+/// How long a run is locked for at a time. The instance running it renews the
+/// lock every third of it, so a run whose lock expired was interrupted.
+const RUN_LEASE: Duration = Duration::from_secs(60);
+
+/// How long shutdown waits for running jobs before aborting them.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
+
+static SCHEDULER: OnceLock<Arc<Scheduler>> = OnceLock::new();
+
+/// A chore run by the scheduler. Each job has a `sys_job` row, created from
+/// `default_cron` on first start, which administrators can reschedule, pause
+/// and trigger.
+#[async_trait]
+pub trait Job: Send + Sync + 'static {
+    /// The unique code of the job, stored as `sys_job.code`.
+    fn code(&self) -> &'static str;
+
+    fn default_cron(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    /// Runs the job once, returning a summary for its run history.
+    async fn run(&self, state: &AppState) -> anyhow::Result<String>;
+}
+
+fn registry() -> Vec<Arc<dyn Job>> {
+    vec![
+        Arc::new(purge::PurgeStaleUploads),
+        Arc::new(purge::PurgeReadNotifications),
+        Arc::new(purge::PurgeJobRuns),
+        Arc::new(purge::PurgeSucceededTasks),
+    ]
+}
+
+/// Parses a cron expression, naming the problem on failure.
+pub fn parse_cron(cron: &str) -> Result<Schedule, String> {
+    Schedule::from_str(cron).map_err(|e| format!("cron: {e}"))
+}
+
+/// The first time `cron` fires after `after`, both in UTC.
+pub fn next_run(cron: &str, after: NaiveDateTime) -> Option<NaiveDateTime> {
+    parse_cron(cron)
+        .ok()?
+        .after(&after.and_utc().with_timezone(&Local))
+        .next()
+        .map(|next| next.naive_utc())
+}
+
+/// Runs the registered jobs on their `sys_job` schedules.
+pub struct Scheduler {
+    state: AppState,
+    jobs: HashMap<&'static str, Arc<dyn Job>>,
+    /// The jobs running in this process, so a job never overlaps itself,
+    /// with the handle to abort the run once it has started.
+    running: Mutex<HashMap<&'static str, Option<AbortHandle>>>,
+    /// The tasks recording the outcome of each run.
+    tasks: Mutex<JoinSet<()>>,
+    /// Wakes the loop when jobs change or on shutdown.
+    wake: Notify,
+    stopping: AtomicBool,
+}
+
+/// The scheduler started by `Scheduler::start`.
+pub fn scheduler() -> &'static Arc<Scheduler> {
+    SCHEDULER.get().expect("The scheduler has not been started")
+}
+
+impl Scheduler {
+    /// Creates the `sys_job` rows of new jobs and starts the scheduling loop.
+    pub async fn start(state: AppState) -> anyhow::Result<Arc<Self>> {
+        let jobs = registry()
+            .into_iter()
+            .map(|job| (job.code(), job))
+            .collect::<HashMap<_, _>>();
+        sync_jobs(&state.db, jobs.values()).await?;
+
+        let scheduler = Arc::new(Self {
+            state,
+            jobs,
+            running: Mutex::default(),
+            tasks: Mutex::default(),
+            wake: Notify::new(),
+            stopping: AtomicBool::new(false),
+        });
+        SCHEDULER
+            .set(scheduler.clone())
+            .map_err(|_| anyhow::anyhow!("The scheduler was already started"))?;
+
+        tokio::spawn(scheduler.clone().run());
+
+        Ok(scheduler)
+    }
+
+    /// Re-reads the schedules, after a job was changed.
+    pub fn reload(&self) {
+        self.wake.notify_one();
+    }
+
+    pub fn is_registered(&self, code: &str) -> bool {
+        self.jobs.contains_key(code)
+    }
+
+    pub fn is_running(&self, code: &str) -> bool {
+        self.running
+            .lock()
+            .expect("Running jobs lock poisoned")
+            .contains_key(code)
+    }
+
+    /// Starts a run of `job` now, returning `None` if it is running already
+    /// or the scheduler is stopping.
+    pub async fn trigger(
+        &self,
+        job: &sys_job::Model,
+        trigger: JobTrigger,
+    ) -> anyhow::Result<Option<sys_job_run::Model>> {
+        let Some(handler) = self.jobs.get(job.code.as_str()).cloned() else {
+            anyhow::bail!("No handler is registered for job `{}`", job.code);
+        };
+        if self.stopping.load(Ordering::Acquire) {
+            return Ok(None);
+        }
+        match self
+            .running
+            .lock()
+            .expect("Running jobs lock poisoned")
+            .entry(handler.code())
+        {
+            Entry::Occupied(_) => return Ok(None),
+            Entry::Vacant(entry) => entry.insert(None),
+        };
+
+        let run = match self.record_start(job, trigger).await {
+            Ok(run) => run,
+            Err(e) => {
+                self.finish(handler.code());
+                return Err(e);
+            }
+        };
+
+        let state = self.state.clone();
+        let execution = tokio::spawn({
+            let handler = handler.clone();
+            async move { handler.run(&state).await }
+        });
+        self.running
+            .lock()
+            .expect("Running jobs lock poisoned")
+            .insert(handler.code(), Some(execution.abort_handle()));
+
+        let db = self.state.db.clone();
+        let record = run.clone();
+        let mut tasks = self.tasks.lock().expect("Job tasks lock poisoned");
+        while tasks.try_join_next().is_some() {}
+        tasks.spawn(async move {
+            let mut execution = execution;
+            let mut renew = tokio::time::interval(RUN_LEASE / 3);
+            renew.tick().await;
+            let result = loop {
+                tokio::select! {
+                    result = &mut execution => break result,
+                    _ = renew.tick() => renew_lock(&db, &record.id).await,
+                }
+            };
+
+            // The job runs in its own task, so a panic fails the run rather
+            // than losing it.
+            let (status, message) = match result {
+                Ok(Ok(summary)) => (JobStatus::Succeeded, summary),
+                Ok(Err(e)) => (JobStatus::Failed, format!("{e:#}")),
+                Err(e) if e.is_cancelled() => {
+                    (JobStatus::Failed, "Aborted at shutdown".to_string())
+                }
+                Err(e) => (JobStatus::Failed, format!("The job panicked: {e}")),
+            };
+            if status == JobStatus::Failed {
+                warn!(job = handler.code(), "Job failed: {message}");
+            }
+
+            let mut run = record.into_active_model();
+            run.status = ActiveValue::Set(status);
+            run.message = ActiveValue::Set(Some(message));
+            run.finished_at = ActiveValue::Set(Some(Utc::now().naive_utc()));
+            run.locked_until = ActiveValue::Set(None);
+            if let Err(e) = run.update(&db).await {
+                error!(job = handler.code(), error = ?e, "Failed to record the job result");
+            }
+            scheduler().finish(handler.code());
+        });
+
+        Ok(Some(run))
+    }
+
+    async fn record_start(
+        &self,
+        job: &sys_job::Model,
+        trigger: JobTrigger,
+    ) -> anyhow::Result<sys_job_run::Model> {
+        let db = &self.state.db;
+        let run = sys_job_run::ActiveModel {
+            job_id: ActiveValue::Set(job.id.clone()),
+            trigger: ActiveValue::Set(trigger),
+            status: ActiveValue::Set(JobStatus::Running),
+            locked_until: ActiveValue::Set(Some(lock_expiry())),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .context("Record job run")?;
+
+        SysJob::update_many()
+            .col_expr(sys_job::Column::LastRunAt, Expr::value(run.started_at))
+            .filter(sys_job::Column::Id.eq(&job.id))
+            .exec(db)
+            .await
+            .context("Update the job's last run")?;
+
+        Ok(run)
+    }
+
+    fn finish(&self, code: &str) {
+        self.running
+            .lock()
+            .expect("Running jobs lock poisoned")
+            .remove(code);
+    }
+
+    async fn run(self: Arc<Self>) {
+        loop {
+            if self.stopping.load(Ordering::Acquire) {
+                break;
+            }
+
+            let idle = match self.run_due().await {
+                Ok(idle) => idle,
+                Err(e) => {
+                    error!(error = ?e, "Failed to run scheduled jobs");
+                    MAX_IDLE
+                }
+            };
+            let _ = tokio::time::timeout(idle, self.wake.notified()).await;
+        }
+    }
+
+    /// Starts the jobs that are due, returning how long until the next one.
+    async fn run_due(&self) -> anyhow::Result<Duration> {
+        let db = &self.state.db;
+        let now = Utc::now().naive_utc();
+        let jobs = SysJob::find()
+            .filter(sys_job::Column::Enabled.eq(true))
+            .filter(sys_job::Column::NextRunAt.is_not_null())
+            .order_by_asc(sys_job::Column::NextRunAt)
+            .all(db)
+            .await
+            .context("Find scheduled jobs")?;
+
+        let mut idle = MAX_IDLE;
+        for job in jobs {
+            let Some(due) = job.next_run_at else {
+                continue;
+            };
+            if due > now {
+                idle = idle.min((due - now).to_std().unwrap_or_default());
+                continue;
+            }
+
+            // Claiming the occurrence by moving `next_run_at` on only if it is
+            // unchanged keeps other instances from running it too.
+            let next = next_run(&job.cron, now);
+            let claimed = SysJob::update_many()
+                .col_expr(sys_job::Column::NextRunAt, Expr::value(next))
+                .filter(sys_job::Column::Id.eq(&job.id))
+                .filter(sys_job::Column::NextRunAt.eq(due))
+                .exec(db)
+                .await
+                .context("Claim the job")?
+                .rows_affected
+                == 1;
+            if let Some(next) = next {
+                idle = idle.min((next - now).to_std().unwrap_or_default());
+            }
+            if !claimed || !self.is_registered(&job.code) {
+                continue;
+            }
+
+            // A failure to start one job must not hold up the others.
+            match self.trigger(&job, JobTrigger::Schedule).await {
+                Ok(Some(_)) => {}
+                Ok(None) => info!(
+                    job = job.code,
+                    "Skipped a run, the previous one is still running"
+                ),
+                Err(e) => error!(job = job.code, error = ?e, "Failed to start a scheduled run"),
+            }
+        }
+
+        Ok(idle)
+    }
+
+    /// Stops scheduling and waits for the running jobs, aborting those still
+    /// running after `SHUTDOWN_GRACE`.
+    pub async fn shutdown(&self) {
+        self.stopping.store(true, Ordering::Release);
+        self.wake.notify_one();
+
+        let mut tasks = std::mem::take(&mut *self.tasks.lock().expect("Job tasks lock poisoned"));
+        while tasks.try_join_next().is_some() {}
+        if tasks.is_empty() {
+            return;
+        }
+        info!("Waiting for {} running jobs", tasks.len());
+        let finished = tokio::time::timeout(SHUTDOWN_GRACE, async {
+            while tasks.join_next().await.is_some() {}
+        })
+        .await;
+        if finished.is_err() {
+            warn!("Aborting {} jobs still running", tasks.len());
+            for execution in self
+                .running
+                .lock()
+                .expect("Running jobs lock poisoned")
+                .values()
+                .flatten()
+            {
+                execution.abort();
+            }
+            // Lets the aborted runs be recorded as such.
+            while tasks.join_next().await.is_some() {}
+        }
+    }
+}
+
+/// When a run locked now stops being vouched for.
+fn lock_expiry() -> NaiveDateTime {
+    Utc::now().naive_utc() + RUN_LEASE
+}
+
+/// Extends the lock of a run that is still going.
+async fn renew_lock(db: &DatabaseConnection, run_id: &str) {
+    let result = SysJobRun::update_many()
+        .col_expr(sys_job_run::Column::LockedUntil, Expr::value(lock_expiry()))
+        .filter(sys_job_run::Column::Id.eq(run_id))
+        .filter(sys_job_run::Column::Status.eq(JobStatus::Running))
+        .exec(db)
+        .await;
+    if let Err(e) = result {
+        warn!(run = run_id, error = ?e, "Failed to renew the lock of a job run");
+    }
+}
+
+/// Creates the rows of newly registered jobs, and marks runs whose lock
+/// expired as failed. Runs of other instances that are still going keep
+/// their lock renewed.
+async fn sync_jobs<'a>(
+    db: &DatabaseConnection,
+    jobs: impl Iterator<Item = &'a Arc<dyn Job>>,
+) -> anyhow::Result<()> {
+    let existing = SysJob::find()
+        .all(db)
+        .await
+        .context("Load jobs")?
+        .into_iter()
+        .map(|job| job.code)
+        .collect::<HashSet<_>>();
+
+    let now = Utc::now().naive_utc();
+    for job in jobs.filter(|job| !existing.contains(job.code())) {
+        parse_cron(job.default_cron())
+            .map_err(|e| anyhow::anyhow!("Job `{}` has an invalid {e}", job.code()))?;
+        sys_job::ActiveModel {
+            code: ActiveValue::Set(job.code().to_string()),
+            cron: ActiveValue::Set(job.default_cron().to_string()),
+            enabled: ActiveValue::Set(true),
+            remark: ActiveValue::Set(Some(job.description().to_string())),
+            next_run_at: ActiveValue::Set(next_run(job.default_cron(), now)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .with_context(|| format!("Create job `{}`", job.code()))?;
+    }
+
+    SysJobRun::update_many()
+        .col_expr(sys_job_run::Column::Status, Expr::value(JobStatus::Failed))
+        .col_expr(
+            sys_job_run::Column::Message,
+            Expr::value("Interrupted by a restart"),
+        )
+        .col_expr(sys_job_run::Column::FinishedAt, Expr::value(now))
+        .filter(sys_job_run::Column::Status.eq(JobStatus::Running))
+        .filter(
+            Condition::any()
+                .add(sys_job_run::Column::LockedUntil.is_null())
+                .add(sys_job_run::Column::LockedUntil.lt(now)),
+        )
+        .exec(db)
+        .await
+        .context("Close interrupted job runs")?;
+
+    Ok(())
+}
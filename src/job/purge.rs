@@ -0,0 +1,135 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{TimeDelta, Utc};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::{
+    app::{AppState, upload},
+    entity::{
+        prelude::*, sys_file_upload, sys_job_run, sys_notification, sys_task,
+        task_status::TaskStatus,
+    },
+    job::Job,
+};
+
+/// Removes resumable uploads that were abandoned before completing.
+pub struct PurgeStaleUploads;
+
+#[async_trait]
+impl Job for PurgeStaleUploads {
+    fn code(&self) -> &'static str {
+        "purge_stale_uploads"
+    }
+
+    fn default_cron(&self) -> &'static str {
+        "0 0 * * * *"
+    }
+
+    fn description(&self) -> &'static str {
+        "Removes resumable uploads not completed within a day"
+    }
+
+    async fn run(&self, AppState { db, storage, .. }: &AppState) -> anyhow::Result<String> {
+        let cutoff = Utc::now().naive_utc() - TimeDelta::days(1);
+        let uploads = SysFileUpload::find()
+            .filter(sys_file_upload::Column::CreatedAt.lt(cutoff))
+            .all(db)
+            .await
+            .context("Find stale uploads")?;
+
+        for upload in &uploads {
+            upload::remove_upload(db, storage.as_ref(), upload).await?;
+        }
+
+        Ok(format!("Removed {} uploads", uploads.len()))
+    }
+}
+
+/// Deletes notifications read long ago.
+pub struct PurgeReadNotifications;
+
+#[async_trait]
+impl Job for PurgeReadNotifications {
+    fn code(&self) -> &'static str {
+        "purge_read_notifications"
+    }
+
+    fn default_cron(&self) -> &'static str {
+        "0 30 3 * * *"
+    }
+
+    fn description(&self) -> &'static str {
+        "Deletes notifications read more than 90 days ago"
+    }
+
+    async fn run(&self, AppState { db, .. }: &AppState) -> anyhow::Result<String> {
+        let cutoff = Utc::now().naive_utc() - TimeDelta::days(90);
+        let result = SysNotification::delete_many()
+            .filter(sys_notification::Column::ReadAt.lt(cutoff))
+            .exec(db)
+            .await
+            .context("Delete read notifications")?;
+
+        Ok(format!("Deleted {} notifications", result.rows_affected))
+    }
+}
+
+/// Keeps the job run history from growing without bound.
+pub struct PurgeJobRuns;
+
+#[async_trait]
+impl Job for PurgeJobRuns {
+    fn code(&self) -> &'static str {
+        "purge_job_runs"
+    }
+
+    fn default_cron(&self) -> &'static str {
+        "0 0 4 * * *"
+    }
+
+    fn description(&self) -> &'static str {
+        "Deletes job runs finished more than 30 days ago"
+    }
+
+    async fn run(&self, AppState { db, .. }: &AppState) -> anyhow::Result<String> {
+        let cutoff = Utc::now().naive_utc() - TimeDelta::days(30);
+        let result = SysJobRun::delete_many()
+            .filter(sys_job_run::Column::FinishedAt.lt(cutoff))
+            .exec(db)
+            .await
+            .context("Delete job runs")?;
+
+        Ok(format!("Deleted {} job runs", result.rows_affected))
+    }
+}
+
+/// Deletes tasks that succeeded long ago. Dead tasks are kept until an
+/// administrator retries them.
+pub struct PurgeSucceededTasks;
+
+#[async_trait]
+impl Job for PurgeSucceededTasks {
+    fn code(&self) -> &'static str {
+        "purge_succeeded_tasks"
+    }
+
+    fn default_cron(&self) -> &'static str {
+        "0 15 4 * * *"
+    }
+
+    fn description(&self) -> &'static str {
+        "Deletes tasks that succeeded more than 7 days ago"
+    }
+
+    async fn run(&self, AppState { db, .. }: &AppState) -> anyhow::Result<String> {
+        let cutoff = Utc::now().naive_utc() - TimeDelta::days(7);
+        let result = SysTask::delete_many()
+            .filter(sys_task::Column::Status.eq(TaskStatus::Succeeded))
+            .filter(sys_task::Column::FinishedAt.lt(cutoff))
+            .exec(db)
+            .await
+            .context("Delete succeeded tasks")?;
+
+        Ok(format!("Deleted {} tasks", result.rows_affected))
+    }
+}
@@ -1,6 +1,46 @@
+use std::time::SystemTime;
+
+use anyhow::Context;
+use axum::http::HeaderMap;
+use opentelemetry::{
+    KeyValue, global,
+    trace::{Span, SpanKind, Status, TraceContextExt, Tracer, TracerProvider},
+};
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    Resource,
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracerProvider},
+};
+use sea_orm::{DbBackend, metric};
+use tracing::{error, info};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
-pub fn init() {
+use crate::config::{
+    self,
+    telemetry::{OtlpProtocol, TelemetryConfig},
+};
+
+/// The instrumentation scope of the spans created here.
+const TRACER: &str = "rust-web";
+
+/// Keeps the trace exporter, if any, to flush it on shutdown.
+pub struct Logger {
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+/// Logs to stdout and, when `[telemetry]` is enabled, exports spans to an
+/// OpenTelemetry collector. Call it inside the Tokio runtime, which the gRPC
+/// exporter runs on.
+pub fn init() -> anyhow::Result<Logger> {
+    let config = config::get().telemetry();
+    let tracer_provider = config
+        .enable()
+        .then(|| tracer_provider(config))
+        .transpose()?;
+
     tracing_subscriber::registry()
         .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
         .with(
@@ -12,5 +52,180 @@ pub fn init() {
                 .with_thread_names(true)
                 .with_target(false),
         )
+        .with(
+            tracer_provider.as_ref().map(|provider| {
+                tracing_opentelemetry::layer().with_tracer(provider.tracer(TRACER))
+            }),
+        )
         .init();
+
+    if config.enable() {
+        info!(
+            endpoint = config.endpoint(),
+            protocol = ?config.protocol(),
+            "Exporting traces over OTLP"
+        );
+    }
+
+    Ok(Logger { tracer_provider })
+}
+
+fn tracer_provider(config: &TelemetryConfig) -> anyhow::Result<SdkTracerProvider> {
+    let exporter = match config.protocol() {
+        OtlpProtocol::Grpc => SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(config.endpoint())
+            .build(),
+        OtlpProtocol::Http => SpanExporter::builder()
+            .with_http()
+            .with_endpoint(config.endpoint())
+            .build(),
+    }
+    .context("Build the OTLP span exporter")?;
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sample_ratio(),
+        ))))
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name().to_string())
+                .build(),
+        )
+        .build();
+
+    global::set_tracer_provider(provider.clone());
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    Ok(provider)
+}
+
+impl Logger {
+    /// Exports the spans still buffered.
+    pub async fn shutdown(self) {
+        let Some(provider) = self.tracer_provider else {
+            return;
+        };
+        // Shutting down blocks until the exporter, which may need the
+        // runtime, has sent the last batch.
+        match tokio::task::spawn_blocking(move || provider.shutdown()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!(error = %e, "Failed to flush traces"),
+            Err(e) => error!(error = %e, "Failed to flush traces"),
+        }
+    }
+}
+
+/// Makes `span` continue the trace of the W3C `traceparent` header, if the
+/// request has one. Does nothing unless traces are exported.
+pub fn continue_trace(span: &tracing::Span, headers: &HeaderMap) {
+    let parent =
+        global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    if parent.span().span_context().is_valid() {
+        let _ = span.set_parent(parent);
+    }
+}
+
+/// Exports a span for a query that just ran, as a child of the current span.
+/// SeaORM reports a query only once it finished, so the span is created with
+/// its start time in the past. Queries outside of a trace, such as those of
+/// the task queue polling, are skipped.
+pub fn record_query(info: &metric::Info<'_>) {
+    let parent = tracing::Span::current().context();
+    if !parent.span().span_context().is_valid() {
+        return;
+    }
+
+    let end = SystemTime::now();
+    let sql = &info.statement.sql;
+    let operation = sql
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+    let system = match info.statement.db_backend {
+        DbBackend::Postgres => "postgresql",
+        DbBackend::Sqlite => "sqlite",
+        DbBackend::MySql => "mysql",
+    };
+
+    let tracer = global::tracer(TRACER);
+    let mut span = tracer
+        .span_builder(operation.clone())
+        .with_kind(SpanKind::Client)
+        .with_start_time(end - info.elapsed)
+        .with_attributes([
+            KeyValue::new("db.system.name", system),
+            KeyValue::new("db.operation.name", operation),
+            KeyValue::new("db.query.text", sql.clone()),
+        ])
+        .start_with_context(&tracer, &parent);
+    if info.failed {
+        span.set_status(Status::error("Query failed"));
+    }
+    span.end_with_timestamp(end);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{Router, body::Bytes, http::StatusCode, routing};
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    /// Serves an OTLP/HTTP collector on a local port, returning its trace
+    /// endpoint and the request bodies it receives.
+    async fn collector() -> (String, mpsc::UnboundedReceiver<Bytes>) {
+        let (sender, received) = mpsc::unbounded_channel();
+        let router = Router::new().route(
+            "/v1/traces",
+            routing::post(move |body: Bytes| async move {
+                let _ = sender.send(body);
+                StatusCode::OK
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Bind the collector");
+        let address = listener.local_addr().expect("Read the collector address");
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        (format!("http://{address}/v1/traces"), received)
+    }
+
+    fn contains(haystack: &[u8], needle: &str) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle.as_bytes())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn exports_spans_to_the_collector() {
+        let (endpoint, mut received) = collector().await;
+        let config = TelemetryConfig {
+            enable: true,
+            protocol: Some(OtlpProtocol::Http),
+            endpoint: Some(endpoint),
+            service_name: Some("logger-test".to_string()),
+            sample_ratio: None,
+        };
+
+        let provider = tracer_provider(&config).unwrap();
+        provider.tracer(TRACER).in_span("exported-span", |_| {});
+        Logger {
+            tracer_provider: Some(provider),
+        }
+        .shutdown()
+        .await;
+
+        let body = tokio::time::timeout(Duration::from_secs(5), received.recv())
+            .await
+            .expect("The collector should receive the spans")
+            .unwrap();
+        assert!(contains(&body, "exported-span"));
+        assert!(contains(&body, "logger-test"));
+    }
 }
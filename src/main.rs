@@ -2,9 +2,21 @@ mod api;
 mod app;
 mod config;
 mod database;
+mod dict;
 mod entity;
+mod event;
+mod health;
+mod job;
 mod logger;
+mod metrics;
+mod notify;
+mod push;
+mod queue;
+mod search;
+mod setting;
+mod storage;
 mod web;
+mod webhook;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -0,0 +1,203 @@
+use std::{sync::LazyLock, time::Instant};
+
+use axum::{
+    Router,
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use sea_orm::DatabaseConnection;
+
+use crate::app::{AppState, error::ApiResult};
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Buckets for request latencies in seconds, from quick lookups to uploads.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    http_requests_in_flight: IntGauge,
+    db_connections: IntGaugeVec,
+    db_max_connections: IntGauge,
+    logins: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let metrics = Self {
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests handled"),
+                &["method", "route", "status"],
+            )
+            .expect("Valid metric"),
+            http_request_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "http_request_duration_seconds",
+                    "Time until the response started",
+                )
+                .buckets(LATENCY_BUCKETS.to_vec()),
+                &["method", "route", "status"],
+            )
+            .expect("Valid metric"),
+            http_requests_in_flight: IntGauge::new(
+                "http_requests_in_flight",
+                "HTTP requests being handled",
+            )
+            .expect("Valid metric"),
+            db_connections: IntGaugeVec::new(
+                Opts::new("db_pool_connections", "Database connections in the pool"),
+                &["state"],
+            )
+            .expect("Valid metric"),
+            db_max_connections: IntGauge::new(
+                "db_pool_max_connections",
+                "Most connections the database pool opens",
+            )
+            .expect("Valid metric"),
+            logins: IntCounterVec::new(Opts::new("logins_total", "Login attempts"), &["outcome"])
+                .expect("Valid metric"),
+            registry,
+        };
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 6] = [
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.http_request_duration.clone()),
+            Box::new(metrics.http_requests_in_flight.clone()),
+            Box::new(metrics.db_connections.clone()),
+            Box::new(metrics.db_max_connections.clone()),
+            Box::new(metrics.logins.clone()),
+        ];
+        for collector in collectors {
+            metrics
+                .registry
+                .register(collector)
+                .expect("Metric names are unique");
+        }
+        #[cfg(target_os = "linux")]
+        metrics
+            .registry
+            .register(Box::new(
+                prometheus::process_collector::ProcessCollector::for_self(),
+            ))
+            .expect("Metric names are unique");
+
+        metrics
+    }
+}
+
+/// Counts a login attempt, by whether it succeeded.
+pub fn record_login(succeeded: bool) {
+    let outcome = if succeeded { "success" } else { "failure" };
+    METRICS.logins.with_label_values(&[outcome]).inc();
+}
+
+/// Middleware recording the count and latency of requests by method, route
+/// template and status. Add it with `route_layer` so that the matched route
+/// is known, which also keeps arbitrary unmatched paths out of the labels.
+pub async fn track(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+
+    let in_flight = InFlight::start();
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = started.elapsed().as_secs_f64();
+    drop(in_flight);
+
+    let status = response.status().as_u16().to_string();
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    METRICS.http_requests.with_label_values(&labels).inc();
+    METRICS
+        .http_request_duration
+        .with_label_values(&labels)
+        .observe(elapsed);
+
+    response
+}
+
+/// Counts a request as in flight until dropped, which also happens when the
+/// client goes away before the response.
+struct InFlight;
+
+impl InFlight {
+    fn start() -> Self {
+        METRICS.http_requests_in_flight.inc();
+        Self
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        METRICS.http_requests_in_flight.dec();
+    }
+}
+
+/// Reads the usage of the connection pool, which sqlx only reports on demand.
+fn observe_pool(db: &DatabaseConnection) {
+    let (size, idle, max) = match db {
+        DatabaseConnection::SqlxPostgresPoolConnection(_) => {
+            let pool = db.get_postgres_connection_pool();
+            (
+                pool.size(),
+                pool.num_idle(),
+                pool.options().get_max_connections(),
+            )
+        }
+        DatabaseConnection::SqlxSqlitePoolConnection(_) => {
+            let pool = db.get_sqlite_connection_pool();
+            (
+                pool.size(),
+                pool.num_idle(),
+                pool.options().get_max_connections(),
+            )
+        }
+        _ => return,
+    };
+    let idle = idle as i64;
+
+    METRICS
+        .db_connections
+        .with_label_values(&["idle"])
+        .set(idle);
+    METRICS
+        .db_connections
+        .with_label_values(&["in_use"])
+        .set(i64::from(size) - idle);
+    METRICS.db_max_connections.set(i64::from(max));
+}
+
+pub fn create_router() -> Router<AppState> {
+    Router::new().route("/metrics", routing::get(get_metrics))
+}
+
+/// Every metric in the Prometheus text format.
+async fn get_metrics(State(AppState { db, .. }): State<AppState>) -> ApiResult<impl IntoResponse> {
+    observe_pool(&db);
+
+    let mut body = Vec::new();
+    let encoder = TextEncoder::new();
+    encoder
+        .encode(&METRICS.registry.gather(), &mut body)
+        .map_err(anyhow::Error::from)?;
+
+    Ok((
+        [(header::CONTENT_TYPE, encoder.format_type().to_string())],
+        body,
+    ))
+}
@@ -0,0 +1,122 @@
+use anyhow::Context;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ConnectionTrait, EntityTrait, JsonValue, prelude::Uuid,
+};
+use serde::Serialize;
+
+use crate::{
+    entity::{prelude::*, sys_notification},
+    push,
+};
+
+/// The rows a single `INSERT` of `notify_many` writes at most, keeping the
+/// bound parameters within SQLite's limit.
+const BATCH_SIZE: usize = 500;
+
+/// The content of a notification, before it is addressed to users.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notice {
+    pub category: String,
+    pub title: String,
+    pub content: String,
+}
+
+impl Notice {
+    pub fn new(
+        category: impl Into<String>,
+        title: impl Into<String>,
+        content: impl Into<String>,
+    ) -> Self {
+        Self {
+            category: category.into(),
+            title: title.into(),
+            content: content.into(),
+        }
+    }
+
+    /// An account security event, such as a password change.
+    pub fn security(title: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new("security", title, content)
+    }
+
+    pub fn announcement(title: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new("announcement", title, content)
+    }
+}
+
+/// The push announcing stored notifications to their users. `send` it once
+/// the transaction storing them commits, so that clients are never told of
+/// a notification that was rolled back.
+#[must_use = "the push is only sent by `send`"]
+pub struct Push {
+    user_ids: Vec<String>,
+    event: &'static str,
+    data: JsonValue,
+}
+
+impl Push {
+    pub fn send(self) {
+        push::send_to_many(self.user_ids, self.event, self.data);
+    }
+}
+
+/// Adds an unread notification to the inbox of `user_id`, returning its push
+/// to their connections as a `notification` message. Pass a transaction to
+/// store it only if the change being reported commits.
+pub async fn notify<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    notice: Notice,
+) -> anyhow::Result<Push> {
+    let notification = sys_notification::ActiveModel {
+        user_id: ActiveValue::Set(user_id.to_string()),
+        category: ActiveValue::Set(notice.category),
+        title: ActiveValue::Set(notice.title),
+        content: ActiveValue::Set(notice.content),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .context("Create notification")?;
+
+    Ok(Push {
+        user_ids: vec![user_id.to_string()],
+        event: "notification",
+        data: serde_json::to_value(&notification).context("Serialize the notification")?,
+    })
+}
+
+/// Adds the same notification to the inbox of each of `user_ids`, returning
+/// a single `notifications` push of the notice to all of them.
+pub async fn notify_many<C: ConnectionTrait>(
+    db: &C,
+    user_ids: Vec<String>,
+    notice: Notice,
+) -> anyhow::Result<Push> {
+    let now = chrono::Utc::now().naive_utc();
+
+    for user_ids in user_ids.chunks(BATCH_SIZE) {
+        // Bulk inserts skip `before_save`, so the generated columns are set
+        // here.
+        SysNotification::insert_many(user_ids.iter().map(|user_id| {
+            sys_notification::ActiveModel {
+                id: ActiveValue::Set(Uuid::now_v7().simple().to_string()),
+                user_id: ActiveValue::Set(user_id.clone()),
+                category: ActiveValue::Set(notice.category.clone()),
+                title: ActiveValue::Set(notice.title.clone()),
+                content: ActiveValue::Set(notice.content.clone()),
+                read_at: ActiveValue::Set(None),
+                created_at: ActiveValue::Set(now),
+            }
+        }))
+        .exec(db)
+        .await
+        .context("Create notifications")?;
+    }
+
+    Ok(Push {
+        user_ids,
+        event: "notifications",
+        data: serde_json::to_value(&notice).context("Serialize the notice")?,
+    })
+}
@@ -0,0 +1,152 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use async_trait::async_trait;
+use sea_orm::JsonValue;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
+use tracing::warn;
+
+use crate::{
+    app::AppState,
+    event::{Subscriber, UserUpdated},
+};
+
+/// How many recent messages are kept for clients reconnecting with
+/// `Last-Event-ID`, and how far a subscriber may fall behind before it is
+/// disconnected to catch up that way.
+const HISTORY_SIZE: usize = 1024;
+
+static HUB: LazyLock<Hub> = LazyLock::new(Hub::new);
+
+/// A message pushed to connected clients.
+#[derive(Debug, Serialize)]
+pub struct Message {
+    /// Increases with every message. Ids start from the time the process
+    /// started, so they keep increasing across restarts.
+    pub id: u64,
+    pub event: String,
+    pub data: JsonValue,
+    /// The users the message is for, or `None` for everyone.
+    #[serde(skip)]
+    user_ids: Option<HashSet<String>>,
+}
+
+impl Message {
+    fn is_for(&self, user_id: &str) -> bool {
+        self.user_ids
+            .as_ref()
+            .is_none_or(|user_ids| user_ids.contains(user_id))
+    }
+}
+
+/// The in-process pub/sub hub behind `/api/events` and `/api/ws`. Messages
+/// only reach clients connected to this instance.
+struct Hub {
+    sender: broadcast::Sender<Arc<Message>>,
+    /// The recent messages, oldest first. Publishing and subscribing hold
+    /// the lock, so a subscriber sees every message exactly once across its
+    /// replay and its receiver.
+    history: Mutex<(u64, VecDeque<Arc<Message>>)>,
+}
+
+impl Hub {
+    fn new() -> Self {
+        let start = chrono::Utc::now().timestamp_millis().unsigned_abs() * 1000;
+        Self {
+            sender: broadcast::channel(HISTORY_SIZE).0,
+            history: Mutex::new((start, VecDeque::with_capacity(HISTORY_SIZE))),
+        }
+    }
+
+    fn publish(&self, user_ids: Option<HashSet<String>>, event: &str, data: impl Serialize) {
+        let data = match serde_json::to_value(data) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Failed to serialize the `{event}` message: {e}");
+                return;
+            }
+        };
+
+        let mut history = self.history.lock().expect("Push history lock poisoned");
+        let (last_id, messages) = &mut *history;
+        *last_id += 1;
+        let message = Arc::new(Message {
+            id: *last_id,
+            event: event.to_string(),
+            data,
+            user_ids,
+        });
+        if messages.len() == HISTORY_SIZE {
+            messages.pop_front();
+        }
+        messages.push_back(message.clone());
+        // Fails only without subscribers.
+        let _ = self.sender.send(message);
+    }
+}
+
+/// Pushes a message to the connections of `user_id`.
+pub fn send_to(user_id: &str, event: &str, data: impl Serialize) {
+    HUB.publish(Some(HashSet::from([user_id.to_string()])), event, data);
+}
+
+/// Pushes one message to the connections of all of `user_ids`.
+pub fn send_to_many(user_ids: impl IntoIterator<Item = String>, event: &str, data: impl Serialize) {
+    HUB.publish(Some(user_ids.into_iter().collect()), event, data);
+}
+
+/// Pushes a message to every connection.
+pub fn broadcast(event: &str, data: impl Serialize) {
+    HUB.publish(None, event, data);
+}
+
+/// The messages for `user_id`, starting after `last_event_id` if it is still
+/// in the history. The stream ends when the subscriber falls too far behind,
+/// so the client reconnects and replays what it missed.
+pub fn subscribe(
+    user_id: String,
+    last_event_id: Option<u64>,
+) -> impl Stream<Item = Arc<Message>> + Send + 'static {
+    let (replay, receiver) = {
+        let history = HUB.history.lock().expect("Push history lock poisoned");
+        let replay = match last_event_id {
+            Some(last_event_id) => history
+                .1
+                .iter()
+                .filter(|message| message.id > last_event_id && message.is_for(&user_id))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        (replay, HUB.sender.subscribe())
+    };
+
+    let live = BroadcastStream::new(receiver)
+        .map_while(Result::ok)
+        .filter(move |message| message.is_for(&user_id));
+    tokio_stream::iter(replay).chain(live)
+}
+
+/// Sends users their profile when it changes, so their open sessions can
+/// refresh it.
+pub struct RefreshProfile;
+
+#[async_trait]
+impl Subscriber<UserUpdated> for RefreshProfile {
+    fn name(&self) -> &'static str {
+        "push"
+    }
+
+    async fn handle(
+        &self,
+        _state: &AppState,
+        UserUpdated(user): &UserUpdated,
+    ) -> anyhow::Result<()> {
+        send_to(&user.id, "profile", user);
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use sea_orm::JsonValue;
+use serde::{Deserialize, Serialize};
+
+use crate::{app::AppState, queue::Task};
+
+/// The payload of a `Handle` task: an event published after commit, for one
+/// of its subscribers.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Handling {
+    pub event: String,
+    pub subscriber: String,
+    pub payload: JsonValue,
+}
+
+/// Runs a subscriber of an event published with
+/// [`EventBatch::publish`](crate::event::EventBatch::publish).
+pub struct Handle;
+
+#[async_trait]
+impl Task for Handle {
+    fn kind(&self) -> &'static str {
+        "event"
+    }
+
+    async fn run(&self, state: &AppState, payload: JsonValue) -> anyhow::Result<()> {
+        let Handling {
+            event,
+            subscriber,
+            payload,
+        } = serde_json::from_value(payload).context("Parse the event")?;
+
+        state
+            .events
+            .handle_queued(state, &event, &subscriber, payload)
+            .await
+    }
+}
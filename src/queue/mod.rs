@@ -0,0 +1,471 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::{Context, bail};
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, EntityTrait, JsonValue,
+    QueryFilter, QueryOrder, QuerySelect, sea_query::Expr,
+};
+use serde::Serialize;
+use serde_json::json;
+use tokio::{sync::Notify, task::JoinSet};
+use tracing::{error, warn};
+
+use crate::{
+    app::AppState,
+    config,
+    entity::{prelude::*, sys_task, task_status::TaskStatus},
+    health::{self, Check},
+};
+
+pub mod event;
+pub mod notification;
+pub mod webhook;
+
+/// The longest an idle worker waits before looking for tasks again, bounding
+/// how late it notices tasks enqueued by another instance or retried after a
+/// backoff.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many due tasks a worker considers at once, so that a worker losing
+/// the race for one task can claim the next.
+const CLAIM_BATCH: u64 = 8;
+
+/// The delay before retrying a task that failed once, doubled for every
+/// further failure up to `BACKOFF_MAX`.
+const BACKOFF_BASE: Duration = Duration::from_secs(10);
+
+const BACKOFF_MAX: Duration = Duration::from_secs(60 * 60);
+
+/// How long shutdown waits for running tasks. Tasks still running are
+/// abandoned, to be retried once their lock expires.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
+
+static QUEUE: OnceLock<Arc<Queue>> = OnceLock::new();
+
+/// Work too slow for a request, run by the queue's workers from a `sys_task`
+/// row. Tasks are retried when they fail, so they must be safe to run more
+/// than once.
+#[async_trait]
+pub trait Task: Send + Sync + 'static {
+    /// The unique kind of the task, stored as `sys_task.kind`.
+    fn kind(&self) -> &'static str;
+
+    /// How many attempts a task gets before it is dead.
+    fn max_attempts(&self) -> i32 {
+        5
+    }
+
+    /// How long an attempt may take before it is failed. A task whose worker
+    /// disappeared is retried after this long.
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(5 * 60)
+    }
+
+    async fn run(&self, state: &AppState, payload: JsonValue) -> anyhow::Result<()>;
+}
+
+fn registry() -> Vec<Arc<dyn Task>> {
+    vec![
+        Arc::new(event::Handle),
+        Arc::new(notification::Announce),
+        Arc::new(webhook::Deliver),
+    ]
+}
+
+/// Adds a task running `task` with `payload`. Pass a transaction to enqueue
+/// the task only if the change it follows up on commits.
+pub async fn enqueue<C: ConnectionTrait>(
+    db: &C,
+    task: &dyn Task,
+    payload: impl Serialize,
+) -> anyhow::Result<sys_task::Model> {
+    let payload = serde_json::to_value(payload).context("Serialize the task payload")?;
+    let task = sys_task::ActiveModel {
+        kind: ActiveValue::Set(task.kind().to_string()),
+        payload: ActiveValue::Set(payload),
+        status: ActiveValue::Set(TaskStatus::Pending),
+        attempts: ActiveValue::Set(0),
+        max_attempts: ActiveValue::Set(task.max_attempts()),
+        run_at: ActiveValue::Set(Utc::now().naive_utc()),
+        locked_until: ActiveValue::Set(None),
+        last_error: ActiveValue::Set(None),
+        finished_at: ActiveValue::Set(None),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .context("Enqueue task")?;
+    wake();
+
+    Ok(task)
+}
+
+/// Gives a dead or waiting task a fresh set of attempts, starting now.
+/// Returns `None` if the task is running or has succeeded.
+pub async fn retry<C: ConnectionTrait>(
+    db: &C,
+    task: &sys_task::Model,
+) -> anyhow::Result<Option<sys_task::Model>> {
+    if !matches!(task.status, TaskStatus::Pending | TaskStatus::Dead) {
+        return Ok(None);
+    }
+
+    let now = Utc::now().naive_utc();
+    let retried = SysTask::update_many()
+        .col_expr(sys_task::Column::Status, Expr::value(TaskStatus::Pending))
+        .col_expr(sys_task::Column::Attempts, Expr::value(0))
+        .col_expr(sys_task::Column::RunAt, Expr::value(now))
+        .col_expr(
+            sys_task::Column::FinishedAt,
+            Expr::value(Option::<NaiveDateTime>::None),
+        )
+        .col_expr(sys_task::Column::UpdatedAt, Expr::value(now))
+        .filter(sys_task::Column::Id.eq(&task.id))
+        .filter(sys_task::Column::Status.eq(task.status))
+        .filter(sys_task::Column::Attempts.eq(task.attempts))
+        .exec(db)
+        .await
+        .context("Retry task")?
+        .rows_affected
+        == 1;
+    if !retried {
+        // A worker claimed it meanwhile.
+        return Ok(None);
+    }
+    wake();
+
+    Ok(Some(sys_task::Model {
+        status: TaskStatus::Pending,
+        attempts: 0,
+        run_at: now,
+        finished_at: None,
+        updated_at: now,
+        ..task.clone()
+    }))
+}
+
+/// Lets an idle worker of this instance look for tasks right away.
+fn wake() {
+    if let Some(queue) = QUEUE.get() {
+        queue.wake.notify_one();
+    }
+}
+
+/// `delay` after `time`, saturating.
+fn later(time: NaiveDateTime, delay: Duration) -> NaiveDateTime {
+    TimeDelta::from_std(delay)
+        .ok()
+        .and_then(|delay| time.checked_add_signed(delay))
+        .unwrap_or(NaiveDateTime::MAX)
+}
+
+/// The delay before the next attempt, after `attempts` have failed.
+fn backoff(attempts: i32) -> Duration {
+    let exponent = u32::try_from(attempts - 1).unwrap_or(0).min(16);
+    BACKOFF_BASE.saturating_mul(1 << exponent).min(BACKOFF_MAX)
+}
+
+/// Runs the tasks of the registered kinds in a pool of workers. Workers
+/// claim a task by locking its row with a conditional update, so several
+/// instances can share the queue.
+pub struct Queue {
+    state: AppState,
+    tasks: HashMap<&'static str, Arc<dyn Task>>,
+    workers: Mutex<JoinSet<()>>,
+    /// Wakes an idle worker when a task is enqueued, or all on shutdown.
+    wake: Notify,
+    stopping: AtomicBool,
+}
+
+impl Queue {
+    /// Starts `queue.workers` workers.
+    pub fn start(state: AppState) -> anyhow::Result<Arc<Self>> {
+        let queue = Arc::new(Self {
+            state,
+            tasks: registry()
+                .into_iter()
+                .map(|task| (task.kind(), task))
+                .collect(),
+            workers: Mutex::default(),
+            wake: Notify::new(),
+            stopping: AtomicBool::new(false),
+        });
+        QUEUE
+            .set(queue.clone())
+            .map_err(|_| anyhow::anyhow!("The task queue was already started"))?;
+
+        let mut workers = queue.workers.lock().expect("Task workers lock poisoned");
+        for _ in 0..config::get().queue().workers() {
+            workers.spawn(queue.clone().work());
+        }
+        drop(workers);
+        health::register(Arc::new(Workers));
+
+        Ok(queue)
+    }
+
+    async fn work(self: Arc<Self>) {
+        while !self.stopping.load(Ordering::Acquire) {
+            match self.claim().await {
+                Ok(Some((task, handler))) => {
+                    // Another worker may find more due tasks.
+                    self.wake.notify_one();
+                    self.execute(task, handler).await;
+                }
+                Ok(None) => {
+                    let _ = tokio::time::timeout(POLL_INTERVAL, self.wake.notified()).await;
+                }
+                Err(e) => {
+                    error!(error = ?e, "Failed to claim a task");
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    /// Locks the next due task, or a running one whose lock has expired, for
+    /// one attempt.
+    async fn claim(&self) -> anyhow::Result<Option<(sys_task::Model, Arc<dyn Task>)>> {
+        let db = &self.state.db;
+        let now = Utc::now().naive_utc();
+        let candidates = SysTask::find()
+            .filter(sys_task::Column::Kind.is_in(self.tasks.keys().copied()))
+            .filter(
+                Condition::any()
+                    .add(
+                        sys_task::Column::Status
+                            .eq(TaskStatus::Pending)
+                            .and(sys_task::Column::RunAt.lte(now)),
+                    )
+                    .add(
+                        sys_task::Column::Status
+                            .eq(TaskStatus::Running)
+                            .and(sys_task::Column::LockedUntil.lt(now)),
+                    ),
+            )
+            .order_by_asc(sys_task::Column::RunAt)
+            .limit(CLAIM_BATCH)
+            .all(db)
+            .await
+            .context("Find due tasks")?;
+
+        for task in candidates {
+            let handler = self.tasks[task.kind.as_str()].clone();
+            if task.attempts >= task.max_attempts {
+                // The worker of the last attempt disappeared.
+                self.settle(&task, Err("The last attempt was lost".to_string()))
+                    .await?;
+                continue;
+            }
+
+            let locked_until = later(now, handler.timeout());
+            let claimed = SysTask::update_many()
+                .col_expr(sys_task::Column::Status, Expr::value(TaskStatus::Running))
+                .col_expr(sys_task::Column::Attempts, Expr::value(task.attempts + 1))
+                .col_expr(sys_task::Column::LockedUntil, Expr::value(locked_until))
+                .col_expr(sys_task::Column::UpdatedAt, Expr::value(now))
+                .filter(sys_task::Column::Id.eq(&task.id))
+                .filter(sys_task::Column::Status.eq(task.status))
+                .filter(sys_task::Column::Attempts.eq(task.attempts))
+                .exec(db)
+                .await
+                .context("Claim task")?
+                .rows_affected
+                == 1;
+            if claimed {
+                let task = sys_task::Model {
+                    status: TaskStatus::Running,
+                    attempts: task.attempts + 1,
+                    locked_until: Some(locked_until),
+                    updated_at: now,
+                    ..task
+                };
+                return Ok(Some((task, handler)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn execute(&self, task: sys_task::Model, handler: Arc<dyn Task>) {
+        // The task runs in its own task, so a panic fails the attempt rather
+        // than the worker.
+        let state = self.state.clone();
+        let payload = task.payload.clone();
+        let execution = tokio::spawn({
+            let handler = handler.clone();
+            async move { handler.run(&state, payload).await }
+        });
+        let abort = execution.abort_handle();
+
+        let result = match tokio::time::timeout(handler.timeout(), execution).await {
+            Ok(Ok(Ok(()))) => Ok(()),
+            Ok(Ok(Err(e))) => Err(format!("{e:#}")),
+            Ok(Err(e)) => Err(format!("The task panicked: {e}")),
+            Err(_) => {
+                abort.abort();
+                Err("Timed out".to_string())
+            }
+        };
+
+        if let Err(e) = self.settle(&task, result).await {
+            error!(task = task.id, error = ?e, "Failed to record the task result");
+        }
+    }
+
+    /// Records the outcome of the attempt `task` was claimed for, unless the
+    /// lock expired and another worker claimed it since.
+    async fn settle(
+        &self,
+        task: &sys_task::Model,
+        result: Result<(), String>,
+    ) -> anyhow::Result<()> {
+        let now = Utc::now().naive_utc();
+        let mut update = SysTask::update_many()
+            .col_expr(
+                sys_task::Column::LockedUntil,
+                Expr::value(Option::<NaiveDateTime>::None),
+            )
+            .col_expr(sys_task::Column::UpdatedAt, Expr::value(now));
+
+        update = match &result {
+            Ok(()) => update
+                .col_expr(sys_task::Column::Status, Expr::value(TaskStatus::Succeeded))
+                .col_expr(sys_task::Column::FinishedAt, Expr::value(now)),
+            Err(message) if task.attempts >= task.max_attempts => {
+                error!(
+                    task = task.id,
+                    kind = task.kind,
+                    "Task failed all {} attempts: {message}",
+                    task.attempts
+                );
+                update
+                    .col_expr(sys_task::Column::Status, Expr::value(TaskStatus::Dead))
+                    .col_expr(sys_task::Column::LastError, Expr::value(message))
+                    .col_expr(sys_task::Column::FinishedAt, Expr::value(now))
+            }
+            Err(message) => {
+                let delay = backoff(task.attempts);
+                warn!(
+                    task = task.id,
+                    kind = task.kind,
+                    "Task attempt {} failed, retrying in {}s: {message}",
+                    task.attempts,
+                    delay.as_secs()
+                );
+                update
+                    .col_expr(sys_task::Column::Status, Expr::value(TaskStatus::Pending))
+                    .col_expr(sys_task::Column::LastError, Expr::value(message))
+                    .col_expr(sys_task::Column::RunAt, Expr::value(later(now, delay)))
+            }
+        };
+
+        let settled = update
+            .filter(sys_task::Column::Id.eq(&task.id))
+            .filter(sys_task::Column::Status.eq(TaskStatus::Running))
+            .filter(sys_task::Column::Attempts.eq(task.attempts))
+            .exec(&self.state.db)
+            .await
+            .context("Record the task result")?
+            .rows_affected
+            == 1;
+        if !settled {
+            warn!(
+                task = task.id,
+                "Task outlived its lock, its result was discarded"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Stops the workers once their current tasks finish, waiting at most
+    /// `SHUTDOWN_GRACE`.
+    pub async fn shutdown(&self) {
+        self.stopping.store(true, Ordering::Release);
+        self.wake.notify_waiters();
+
+        let mut workers =
+            std::mem::take(&mut *self.workers.lock().expect("Task workers lock poisoned"));
+        let finished = tokio::time::timeout(SHUTDOWN_GRACE, async {
+            while workers.join_next().await.is_some() {}
+        })
+        .await;
+        if finished.is_err() {
+            warn!("Abandoning {} running tasks", workers.len());
+            workers.abort_all();
+        }
+    }
+}
+
+/// Fails when every worker of this instance has died, since its tasks would
+/// wait for another instance.
+struct Workers;
+
+#[async_trait]
+impl Check for Workers {
+    fn name(&self) -> &'static str {
+        "queue"
+    }
+
+    async fn check(&self, _state: &AppState) -> anyhow::Result<JsonValue> {
+        let queue = QUEUE.get().context("The task queue was not started")?;
+        if queue.stopping.load(Ordering::Acquire) {
+            bail!("The task queue is shutting down");
+        }
+
+        let mut workers = queue.workers.lock().expect("Task workers lock poisoned");
+        // Workers only return on shutdown, so any that finished panicked.
+        while let Some(result) = workers.try_join_next() {
+            if let Err(e) = result {
+                error!(error = ?e, "A task worker died");
+            }
+        }
+        let running = workers.len();
+        drop(workers);
+
+        let configured = config::get().queue().workers();
+        if running == 0 {
+            bail!("All {configured} task workers have died");
+        }
+
+        Ok(json!({ "workers": running, "configured": configured }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        assert_eq!(backoff(1), Duration::from_secs(10));
+        assert_eq!(backoff(2), Duration::from_secs(20));
+        assert_eq!(backoff(3), Duration::from_secs(40));
+        assert_eq!(backoff(9), Duration::from_secs(2560));
+        assert_eq!(backoff(10), BACKOFF_MAX);
+        assert_eq!(backoff(i32::MAX), BACKOFF_MAX);
+        // Before any failure, there is nothing to back off from.
+        assert_eq!(backoff(0), BACKOFF_BASE);
+        assert_eq!(backoff(-1), BACKOFF_BASE);
+    }
+
+    #[test]
+    fn later_saturates() {
+        let time = NaiveDateTime::default();
+        assert_eq!(
+            later(time, Duration::from_secs(90)),
+            time + TimeDelta::seconds(90)
+        );
+        assert_eq!(later(time, Duration::MAX), NaiveDateTime::MAX);
+    }
+}
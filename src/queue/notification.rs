@@ -0,0 +1,62 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use sea_orm::{
+    ColumnTrait, EntityTrait, JsonValue, QueryFilter, QuerySelect, QueryTrait, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::AppState,
+    entity::{prelude::*, sys_user},
+    notify::{self, Notice},
+    queue::Task,
+};
+
+/// The payload of an `Announce` task.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Announcement {
+    pub title: String,
+    pub content: String,
+    /// The recipients, or every user when absent.
+    pub user_ids: Option<Vec<String>>,
+}
+
+/// Delivers an announcement to the inbox of its recipients.
+pub struct Announce;
+
+#[async_trait]
+impl Task for Announce {
+    fn kind(&self) -> &'static str {
+        "announce"
+    }
+
+    async fn run(&self, AppState { db, .. }: &AppState, payload: JsonValue) -> anyhow::Result<()> {
+        let announcement: Announcement =
+            serde_json::from_value(payload).context("Parse the announcement")?;
+
+        let user_ids = SysUser::find()
+            .select_only()
+            .column(sys_user::Column::Id)
+            .apply_if(announcement.user_ids, |select, user_ids| {
+                select.filter(sys_user::Column::Id.is_in(user_ids))
+            })
+            .into_tuple::<String>()
+            .all(db)
+            .await
+            .context("Find announcement recipients")?;
+
+        // All or nothing, so a retry does not deliver it twice.
+        let txn = db.begin().await.context("Begin transaction")?;
+        let push = notify::notify_many(
+            &txn,
+            user_ids,
+            Notice::announcement(announcement.title, announcement.content),
+        )
+        .await?;
+        txn.commit().await.context("Commit transaction")?;
+        push.send();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel, JsonValue};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::AppState,
+    entity::{delivery_status::DeliveryStatus, prelude::*},
+    queue::Task,
+    webhook,
+};
+
+/// The payload of a `Deliver` task.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Delivery {
+    pub delivery_id: String,
+}
+
+/// Sends a webhook delivery, failing while the receiver does not accept it
+/// so that the queue retries it with backoff.
+pub struct Deliver;
+
+#[async_trait]
+impl Task for Deliver {
+    fn kind(&self) -> &'static str {
+        "webhook"
+    }
+
+    /// With the queue's backoff, retries span about 20 minutes.
+    fn max_attempts(&self) -> i32 {
+        8
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    async fn run(&self, AppState { db, .. }: &AppState, payload: JsonValue) -> anyhow::Result<()> {
+        let Delivery { delivery_id } =
+            serde_json::from_value(payload).context("Parse the delivery")?;
+        let Some(delivery) = SysWebhookDelivery::find_by_id(&delivery_id)
+            .one(db)
+            .await
+            .context("Find webhook delivery")?
+        else {
+            // Deleted along with its webhook.
+            return Ok(());
+        };
+        let webhook = SysWebhook::find_by_id(&delivery.webhook_id)
+            .one(db)
+            .await
+            .context("Find webhook")?;
+
+        let attempt = match &webhook {
+            Some(webhook) if webhook.enabled => Some(webhook::send(webhook, &delivery).await?),
+            _ => None,
+        };
+
+        let attempts = delivery.attempts + 1;
+        let mut active_model = delivery.into_active_model();
+        active_model.attempts = ActiveValue::Set(attempts);
+        let result = match &attempt {
+            Some(attempt) => {
+                active_model.response_status =
+                    ActiveValue::Set(attempt.response_status.map(i32::from));
+                active_model.response_body = ActiveValue::Set(attempt.response_body.clone());
+                active_model.error = ActiveValue::Set(attempt.error.clone());
+                if attempt.succeeded() {
+                    active_model.status = ActiveValue::Set(DeliveryStatus::Succeeded);
+                    Ok(())
+                } else {
+                    active_model.status = ActiveValue::Set(DeliveryStatus::Failed);
+                    Err(match (&attempt.error, attempt.response_status) {
+                        (Some(error), _) => anyhow::anyhow!("Delivery failed: {error}"),
+                        (None, status) => {
+                            anyhow::anyhow!("The receiver responded with {status:?}")
+                        }
+                    })
+                }
+            }
+            None => {
+                // Not worth retrying.
+                active_model.status = ActiveValue::Set(DeliveryStatus::Failed);
+                active_model.error =
+                    ActiveValue::Set(Some("The webhook was disabled before delivery".to_string()));
+                Ok(())
+            }
+        };
+        active_model
+            .update(db)
+            .await
+            .context("Record the delivery attempt")?;
+
+        result
+    }
+}
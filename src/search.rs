@@ -0,0 +1,313 @@
+use anyhow::Context;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait, IdenStatic, Order,
+    QueryFilter, QueryOrder, QueryTrait, Select, Statement, TransactionTrait,
+    sea_query::{Alias, Expr, JoinType, LikeExpr},
+};
+
+use crate::{
+    config::{self, database::DatabaseType},
+    database,
+    entity::{prelude::*, sys_user},
+};
+
+/// The FTS5 tokenizer option that indexes every three characters, letting
+/// SQLite match terms in the middle of words and of runs of Chinese or digits.
+const TRIGRAM: &str = "tokenize='trigram'";
+
+/// The shortest term a trigram index can match.
+const TRIGRAM_LEN: usize = 3;
+
+/// The columns searched by the user `keyword` filter.
+pub const SYS_USER: SearchIndex<SysUser> = SearchIndex {
+    entity: SysUser,
+    key: sys_user::Column::Id,
+    columns: &[
+        sys_user::Column::Name,
+        sys_user::Column::NamePinyin,
+        sys_user::Column::NameInitials,
+        sys_user::Column::Account,
+        sys_user::Column::MobilePhone,
+    ],
+};
+
+/// A full-text index over some text columns of an entity.
+///
+/// SQLite keeps an FTS5 table `{table}_fts` in sync through triggers, while
+/// Postgres uses a GIN index over a `tsvector` expression, which the database
+/// maintains by itself. SQLite matches search terms anywhere in a column, so
+/// that `三` finds `张三`, while Postgres matches them as prefixes.
+pub struct SearchIndex<E: EntityTrait> {
+    entity: E,
+    key: E::Column,
+    columns: &'static [E::Column],
+}
+
+impl<E: EntityTrait> SearchIndex<E> {
+    fn table(&self) -> &str {
+        self.entity.table_name()
+    }
+
+    fn fts_table(&self) -> String {
+        format!("{}_fts", self.table())
+    }
+
+    fn column_list(&self, prefix: &str) -> String {
+        self.columns
+            .iter()
+            .map(|column| format!("{prefix}{}", column.as_str()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The `tsvector` the Postgres index is built on. Queries must use the
+    /// same expression for the index to apply.
+    fn document(&self) -> String {
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| format!("coalesce({}, '')", column.as_str()))
+            .collect::<Vec<_>>()
+            .join(" || ' ' || ");
+        format!("to_tsvector('simple', {columns})")
+    }
+
+    /// Creates the index when it is missing or covers other columns.
+    pub async fn sync(&self, db: &DatabaseConnection) -> anyhow::Result<()> {
+        match config::get().database().get_type() {
+            DatabaseType::Sqlite => self.sync_fts5(db).await,
+            DatabaseType::Postgres => self.sync_tsvector(db).await,
+        }
+        .with_context(|| format!("Create the search index of {}", self.table()))
+    }
+
+    async fn sync_fts5(&self, db: &DatabaseConnection) -> anyhow::Result<()> {
+        let (table, fts, key) = (self.table(), self.fts_table(), self.key.as_str());
+        let existing = database::existing_columns(db, &fts).await?;
+        let expected = std::iter::once(key)
+            .chain(self.columns.iter().map(|column| column.as_str()))
+            .map(str::to_string)
+            .collect();
+        if existing == expected && self.fts_definition(db).await?.contains(TRIGRAM) {
+            return Ok(());
+        }
+
+        let columns = self.column_list("");
+        let new_columns = self.column_list("new.");
+        let txn = db.begin().await?;
+        for statement in [
+            format!("DROP TRIGGER IF EXISTS {fts}_insert"),
+            format!("DROP TRIGGER IF EXISTS {fts}_update"),
+            format!("DROP TRIGGER IF EXISTS {fts}_delete"),
+            format!("DROP TABLE IF EXISTS {fts}"),
+            format!("CREATE VIRTUAL TABLE {fts} USING fts5({key} UNINDEXED, {columns}, {TRIGRAM})"),
+            format!("INSERT INTO {fts} ({key}, {columns}) SELECT {key}, {columns} FROM {table}"),
+            format!(
+                "CREATE TRIGGER {fts}_insert AFTER INSERT ON {table} BEGIN \
+                 INSERT INTO {fts} ({key}, {columns}) VALUES (new.{key}, {new_columns}); END"
+            ),
+            format!(
+                "CREATE TRIGGER {fts}_update AFTER UPDATE ON {table} BEGIN \
+                 DELETE FROM {fts} WHERE {key} = old.{key}; \
+                 INSERT INTO {fts} ({key}, {columns}) VALUES (new.{key}, {new_columns}); END"
+            ),
+            format!(
+                "CREATE TRIGGER {fts}_delete AFTER DELETE ON {table} BEGIN \
+                 DELETE FROM {fts} WHERE {key} = old.{key}; END"
+            ),
+        ] {
+            txn.execute_unprepared(&statement).await?;
+        }
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    /// The `CREATE` statement of the FTS5 table, empty when it is missing.
+    async fn fts_definition(&self, db: &DatabaseConnection) -> anyhow::Result<String> {
+        let definition = db
+            .query_one(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?",
+                [self.fts_table().into()],
+            ))
+            .await?
+            .map(|row| row.try_get::<String>("", "sql"))
+            .transpose()?;
+
+        Ok(definition.unwrap_or_default())
+    }
+
+    async fn sync_tsvector(&self, db: &DatabaseConnection) -> anyhow::Result<()> {
+        let backend = db.get_database_backend();
+        let (table, index) = (self.table(), format!("{}_search_idx", self.table()));
+        let definition = db
+            .query_one(Statement::from_sql_and_values(
+                backend,
+                "SELECT indexdef FROM pg_indexes \
+                 WHERE schemaname = current_schema() AND indexname = $1",
+                [index.clone().into()],
+            ))
+            .await?
+            .map(|row| row.try_get::<String>("", "indexdef"))
+            .transpose()?;
+
+        match definition {
+            Some(definition)
+                if self
+                    .columns
+                    .iter()
+                    .all(|column| definition.contains(column.as_str())) =>
+            {
+                return Ok(());
+            }
+            Some(_) => {
+                db.execute_unprepared(&format!("DROP INDEX {index}"))
+                    .await?;
+            }
+            None => {}
+        }
+        db.execute_unprepared(&format!(
+            "CREATE INDEX {index} ON {table} USING GIN (({}))",
+            self.document()
+        ))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Restricts `select` to rows matching every term of `keyword`, most
+    /// relevant first when `ranked`. Blank keywords match everything.
+    pub fn apply(&self, mut select: Select<E>, keyword: &str, ranked: bool) -> Select<E> {
+        let terms = keyword.split_whitespace().collect::<Vec<_>>();
+        if terms.is_empty() {
+            return select;
+        }
+
+        match config::get().database().get_type() {
+            DatabaseType::Sqlite => {
+                // Trigrams cannot match shorter terms, which fall back to
+                // scanning the columns.
+                let (long, short): (Vec<_>, Vec<_>) = terms
+                    .into_iter()
+                    .partition(|term| term.chars().count() >= TRIGRAM_LEN);
+                for term in short {
+                    let pattern = format!(
+                        "%{}%",
+                        term.replace('\\', "\\\\")
+                            .replace('%', "\\%")
+                            .replace('_', "\\_")
+                    );
+                    select = select.filter(self.columns.iter().fold(
+                        Condition::any(),
+                        |condition, column| {
+                            condition.add(column.like(LikeExpr::new(&pattern).escape('\\')))
+                        },
+                    ));
+                }
+                if long.is_empty() {
+                    return select;
+                }
+
+                let (table, fts, key) = (self.table(), self.fts_table(), self.key.as_str());
+                let query = long
+                    .iter()
+                    .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                QueryTrait::query(&mut select).join(
+                    JoinType::InnerJoin,
+                    Alias::new(&fts),
+                    Expr::cust_with_values(
+                        format!("{fts}.{key} = {table}.{key} AND {fts} MATCH ?"),
+                        [query],
+                    ),
+                );
+                if ranked {
+                    // FTS5 ranks by bm25, where lower is more relevant.
+                    select = select.order_by(Expr::cust(format!("{fts}.rank")), Order::Asc);
+                }
+                select
+            }
+            DatabaseType::Postgres => {
+                let query = terms
+                    .iter()
+                    .map(|term| format!("'{}':*", term.replace('\\', "\\\\").replace('\'', "''")))
+                    .collect::<Vec<_>>()
+                    .join(" & ");
+                let document = self.document();
+                select = select.filter(Expr::cust_with_values(
+                    format!("{document} @@ to_tsquery('simple', $1)"),
+                    [query.clone()],
+                ));
+                if ranked {
+                    select = select.order_by(
+                        Expr::cust_with_values(
+                            format!("ts_rank({document}, to_tsquery('simple', $1))"),
+                            [query],
+                        ),
+                        Order::Desc,
+                    );
+                }
+                select
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ActiveModelTrait, ActiveValue};
+
+    use super::*;
+    use crate::entity::gender::Gender;
+
+    async fn user(db: &DatabaseConnection, name: &str, account: &str, mobile_phone: &str) {
+        sys_user::ActiveModel {
+            name: ActiveValue::Set(name.to_string()),
+            gender: ActiveValue::Set(Gender::Male),
+            account: ActiveValue::Set(account.to_string()),
+            password: ActiveValue::Set(String::new()),
+            mobile_phone: ActiveValue::Set(mobile_phone.to_string()),
+            birthday: ActiveValue::Set(chrono::NaiveDate::default()),
+            enabled: ActiveValue::Set(true),
+            created_at: ActiveValue::Set(chrono::NaiveDateTime::default()),
+            updated_at: ActiveValue::Set(chrono::NaiveDateTime::default()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    async fn search(db: &DatabaseConnection, keyword: &str) -> Vec<String> {
+        let mut accounts = SYS_USER
+            .apply(SysUser::find(), keyword, true)
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|user| user.account)
+            .collect::<Vec<_>>();
+        accounts.sort();
+        accounts
+    }
+
+    #[tokio::test]
+    async fn matches_terms_inside_words() {
+        let db = database::memory().await;
+        user(&db, "张三", "zhangsan", "13812345678").await;
+        user(&db, "李四", "lisi", "13900000000").await;
+        user(&db, "张三丰", "zhangsanfeng", "13700001234").await;
+
+        assert_eq!(search(&db, "三").await, ["zhangsan", "zhangsanfeng"]);
+        assert_eq!(search(&db, "三丰").await, ["zhangsanfeng"]);
+        assert_eq!(search(&db, "1234").await, ["zhangsan", "zhangsanfeng"]);
+        assert_eq!(search(&db, "2345678").await, ["zhangsan"]);
+        assert_eq!(search(&db, "san").await, ["zhangsan", "zhangsanfeng"]);
+        assert_eq!(search(&db, "三 5678").await, ["zhangsan"]);
+        assert!(search(&db, "四 1234").await.is_empty());
+        // LIKE wildcards in a keyword are matched literally.
+        assert!(search(&db, "%").await.is_empty());
+    }
+}
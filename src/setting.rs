@@ -0,0 +1,112 @@
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{LazyLock, RwLock},
+};
+
+use anyhow::Context;
+use sea_orm::{DatabaseConnection, EntityTrait, JsonValue};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    config,
+    entity::{data_scope::DataScope, prelude::*},
+};
+
+/// The values of `sys_config` by key. It is loaded at startup and reloaded
+/// after each write through the API, so lookups never touch the database.
+static SETTINGS: LazyLock<RwLock<HashMap<String, JsonValue>>> = LazyLock::new(Default::default);
+
+pub const AUTH_EXPIRATION: Setting<u64> = Setting::new("auth.expiration");
+pub const AUTH_DEFAULT_DATA_SCOPE: Setting<DataScope> = Setting::new("auth.default_data_scope");
+pub const STORAGE_MAX_AVATAR_SIZE: Setting<usize> = Setting::new("storage.max_avatar_size");
+pub const STORAGE_MAX_FILE_SIZE: Setting<u64> = Setting::new("storage.max_file_size");
+
+/// The settings that may be stored in `sys_config`.
+const KNOWN: &[&dyn AnySetting] = &[
+    &AUTH_EXPIRATION,
+    &AUTH_DEFAULT_DATA_SCOPE,
+    &STORAGE_MAX_AVATAR_SIZE,
+    &STORAGE_MAX_FILE_SIZE,
+];
+
+/// A configuration entry that can be changed at runtime through `sys_config`,
+/// keyed by its path in `application.toml`.
+///
+/// Values resolve in the order environment, database, file: an `APP_`
+/// variable naming the entry pins it, otherwise a stored value overrides the
+/// file. Accessors in `config` read the override first and fall back to
+/// the loaded configuration, which already covers the environment and file.
+pub struct Setting<T> {
+    key: &'static str,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> Setting<T> {
+    const fn new(key: &'static str) -> Self {
+        Self {
+            key,
+            value: PhantomData,
+        }
+    }
+
+    /// The stored value, unless the environment sets the entry.
+    pub fn get(&self) -> Option<T> {
+        if self.pinned() {
+            return None;
+        }
+
+        let value = SETTINGS
+            .read()
+            .expect("Setting cache lock poisoned")
+            .get(self.key)
+            .cloned()?;
+        serde_json::from_value(value).ok()
+    }
+
+    fn pinned(&self) -> bool {
+        config::is_env_set(self.key)
+    }
+}
+
+trait AnySetting: Sync {
+    fn key(&self) -> &'static str;
+
+    fn accepts(&self, value: &JsonValue) -> bool;
+}
+
+impl<T: DeserializeOwned> AnySetting for Setting<T> {
+    fn key(&self) -> &'static str {
+        self.key
+    }
+
+    fn accepts(&self, value: &JsonValue) -> bool {
+        T::deserialize(value).is_ok()
+    }
+}
+
+/// Checks a value before it is stored, so `Setting::get` does not silently
+/// ignore it.
+pub fn check(key: &str, value: &JsonValue) -> Result<(), String> {
+    let setting = KNOWN
+        .iter()
+        .find(|setting| setting.key() == key)
+        .ok_or_else(|| format!("key: Unknown setting `{key}`"))?;
+    if !setting.accepts(value) {
+        return Err(format!("value: Invalid value for setting `{key}`"));
+    }
+    Ok(())
+}
+
+pub async fn reload(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let settings = SysConfig::find()
+        .all(db)
+        .await
+        .context("Load settings")?
+        .into_iter()
+        .map(|setting| (setting.key, setting.value))
+        .collect();
+    *SETTINGS.write().expect("Setting cache lock poisoned") = settings;
+
+    Ok(())
+}
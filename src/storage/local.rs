@@ -0,0 +1,104 @@
+use std::{
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use axum::body::Bytes;
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+use uuid::Uuid;
+
+use crate::storage::FileStorage;
+
+/// Stores files under a root directory on the local filesystem.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub async fn new(root: &Path) -> anyhow::Result<Self> {
+        fs::create_dir_all(root)
+            .await
+            .with_context(|| format!("Create storage directory {}", root.display()))?;
+
+        Ok(Self {
+            root: root.to_path_buf(),
+        })
+    }
+
+    fn resolve(&self, key: &str) -> anyhow::Result<PathBuf> {
+        let relative = Path::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            anyhow::bail!("Invalid storage key: {key}");
+        }
+
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl FileStorage for LocalStorage {
+    async fn put(&self, key: &str, data: Bytes) -> anyhow::Result<()> {
+        let path = self.resolve(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Create directory {}", parent.display()))?;
+        }
+
+        // Write to a sibling first so that readers never see a partial file.
+        let temp = path.with_file_name(format!(".{}.tmp", Uuid::new_v4().simple()));
+        fs::write(&temp, &data)
+            .await
+            .with_context(|| format!("Write file {}", temp.display()))?;
+        fs::rename(&temp, &path)
+            .await
+            .with_context(|| format!("Move file into {}", path.display()))
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>> {
+        let path = self.resolve(key)?;
+        match fs::read(&path).await {
+            Ok(data) => Ok(Some(Bytes::from(data))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Read file {}", path.display())),
+        }
+    }
+
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> anyhow::Result<Option<Bytes>> {
+        let path = self.resolve(key)?;
+        let mut file = match fs::File::open(&path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Open file {}", path.display())),
+        };
+
+        file.seek(std::io::SeekFrom::Start(start))
+            .await
+            .with_context(|| format!("Seek file {}", path.display()))?;
+        let mut data = Vec::new();
+        file.take(end.saturating_sub(start))
+            .read_to_end(&mut data)
+            .await
+            .with_context(|| format!("Read file {}", path.display()))?;
+
+        Ok(Some(Bytes::from(data)))
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let path = self.resolve(key)?;
+        match fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("Delete file {}", path.display())),
+        }
+    }
+}
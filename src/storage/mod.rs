@@ -0,0 +1,89 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Context, bail};
+use async_trait::async_trait;
+use axum::body::Bytes;
+use sea_orm::JsonValue;
+use serde_json::json;
+
+use crate::{
+    app::AppState,
+    config::{self, storage::StorageType},
+    health::{self, Check},
+};
+
+mod local;
+mod s3;
+#[cfg(test)]
+pub mod testing;
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+/// A flat key/value store for uploaded files. Keys are `/`-separated
+/// relative paths such as `avatars/<user_id>/<file>.png`.
+#[async_trait]
+pub trait FileStorage: Send + Sync {
+    async fn put(&self, key: &str, data: Bytes) -> anyhow::Result<()>;
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>>;
+
+    /// Reads bytes `start..end` of a file, `end` being exclusive.
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> anyhow::Result<Option<Bytes>>;
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+}
+
+pub async fn init() -> anyhow::Result<Arc<dyn FileStorage>> {
+    let config = config::get().storage();
+    let storage: Arc<dyn FileStorage> = match config.get_type() {
+        StorageType::Local => {
+            let storage = LocalStorage::new(config.path()).await?;
+            health::register(Arc::new(DiskSpace {
+                path: config.path().to_path_buf(),
+                min_free: config::get().health().min_free_space(),
+            }));
+            Arc::new(storage)
+        }
+        StorageType::S3 => Arc::new(S3Storage::new(
+            config.s3().context("Missing [storage.s3] configuration")?,
+        )?),
+    };
+
+    Ok(storage)
+}
+
+/// Fails when the volume of local storage is nearly full, since uploads
+/// would start failing.
+struct DiskSpace {
+    path: PathBuf,
+    min_free: u64,
+}
+
+#[async_trait]
+impl Check for DiskSpace {
+    fn name(&self) -> &'static str {
+        "diskSpace"
+    }
+
+    async fn check(&self, _state: &AppState) -> anyhow::Result<JsonValue> {
+        let path = self.path.clone();
+        let free = tokio::task::spawn_blocking(move || fs4::available_space(path))
+            .await
+            .context("Join the disk space check")?
+            .with_context(|| format!("Read the free space of {}", self.path.display()))?;
+        if free < self.min_free {
+            bail!(
+                "{} has {free} bytes free, below the minimum of {}",
+                self.path.display(),
+                self.min_free
+            );
+        }
+
+        Ok(json!({
+            "path": self.path,
+            "free": free,
+            "minFree": self.min_free,
+        }))
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use axum::body::Bytes;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, StatusCode, Url, header::HeaderMap};
+use sha2::{Digest, Sha256};
+
+use crate::{config::storage::S3Config, storage::FileStorage};
+
+/// Stores files in a bucket of an S3-compatible object store, signing each
+/// request with AWS Signature Version 4.
+pub struct S3Storage {
+    client: Client,
+    config: &'static S3Config,
+    endpoint: Url,
+}
+
+impl S3Storage {
+    pub fn new(config: &'static S3Config) -> anyhow::Result<Self> {
+        let endpoint = Url::parse(&config.endpoint)
+            .with_context(|| format!("Parse S3 endpoint {}", config.endpoint))?;
+
+        Ok(Self {
+            client: Client::new(),
+            config,
+            endpoint,
+        })
+    }
+
+    fn object_url(&self, key: &str) -> anyhow::Result<Url> {
+        let mut url = self.endpoint.clone();
+        let path = if self.config.path_style() {
+            format!("/{}/{}", self.config.bucket, uri_encode(key))
+        } else {
+            let host = url.host_str().context("S3 endpoint has no host")?;
+            url.set_host(Some(&format!("{}.{host}", self.config.bucket)))
+                .context("Build virtual-hosted S3 URL")?;
+            format!("/{}", uri_encode(key))
+        };
+        url.set_path(&path);
+
+        Ok(url)
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: Bytes,
+        range: Option<(u64, u64)>,
+    ) -> anyhow::Result<reqwest::Response> {
+        let url = self.object_url(key)?;
+        let headers = self.sign(&method, &url, &body)?;
+
+        let mut request = self.client.request(method, url).headers(headers);
+        if let Some((start, end)) = range {
+            request = request.header("range", format!("bytes={start}-{}", end - 1));
+        }
+
+        request
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("Send S3 request for {key}"))
+    }
+
+    fn sign(&self, method: &Method, url: &Url, body: &[u8]) -> anyhow::Result<HeaderMap> {
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let region = self.config.region();
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let payload_hash = format!("{:x}", Sha256::digest(body));
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{method}\n{}\n\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{signed_headers}\n{payload_hash}",
+            url.path(),
+        );
+        let scope = format!("{date}/{region}/s3/aws4_request");
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{:x}",
+            Sha256::digest(canonical_request.as_bytes()),
+        );
+
+        let key = [date.as_str(), region, "s3", "aws4_request"].iter().fold(
+            format!("AWS4{}", self.config.secret_key).into_bytes(),
+            |key, part| hmac(&key, part.as_bytes()),
+        );
+        let signature = hmac(&key, string_to_sign.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-amz-date", amz_date.parse()?);
+        headers.insert("x-amz-content-sha256", payload_hash.parse()?);
+        headers.insert(
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                self.config.access_key,
+            )
+            .parse()?,
+        );
+
+        Ok(headers)
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes an object key as SigV4 expects, keeping `/` separators.
+fn uri_encode(key: &str) -> String {
+    key.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+async fn check(response: reqwest::Response, action: &str) -> anyhow::Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    anyhow::bail!("S3 {action} failed with {status}: {body}")
+}
+
+#[async_trait]
+impl FileStorage for S3Storage {
+    async fn put(&self, key: &str, data: Bytes) -> anyhow::Result<()> {
+        let response = self.send(Method::PUT, key, data, None).await?;
+        check(response, "put").await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>> {
+        let response = self.send(Method::GET, key, Bytes::new(), None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let data = check(response, "get").await?.bytes().await?;
+        Ok(Some(data))
+    }
+
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> anyhow::Result<Option<Bytes>> {
+        if start >= end {
+            return Ok(Some(Bytes::new()));
+        }
+
+        let response = self
+            .send(Method::GET, key, Bytes::new(), Some((start, end)))
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let data = check(response, "get").await?.bytes().await?;
+        Ok(Some(data))
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let response = self.send(Method::DELETE, key, Bytes::new(), None).await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            check(response, "delete").await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::{FileStorage, testing::FakeS3};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn stores_and_reads_objects() {
+        let s3 = FakeS3::start().await;
+        let storage = &s3.storage;
+
+        storage
+            .put("files/ab/a file.txt", Bytes::from_static(b"hello world"))
+            .await
+            .unwrap();
+        assert_eq!(s3.keys(), ["files/ab/a file.txt"]);
+
+        let data = storage.get("files/ab/a file.txt").await.unwrap();
+        assert_eq!(data.as_deref(), Some(&b"hello world"[..]));
+        let range = storage
+            .get_range("files/ab/a file.txt", 6, 11)
+            .await
+            .unwrap();
+        assert_eq!(range.as_deref(), Some(&b"world"[..]));
+
+        storage.delete("files/ab/a file.txt").await.unwrap();
+        assert!(storage.get("files/ab/a file.txt").await.unwrap().is_none());
+        // Deleting a missing object is not an error.
+        storage.delete("files/ab/a file.txt").await.unwrap();
+    }
+
+    #[test]
+    fn encodes_keys_but_keeps_separators() {
+        assert_eq!(uri_encode("avatars/1/a b+c.png"), "avatars/1/a%20b%2Bc.png");
+    }
+}
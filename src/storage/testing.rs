@@ -0,0 +1,147 @@
+//! An in-process stand-in for an S3-compatible server such as MinIO, to test
+//! code that stores files through `S3Storage`.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{config::storage::S3Config, storage::S3Storage};
+
+pub const BUCKET: &str = "test-bucket";
+const ACCESS_KEY: &str = "minioadmin";
+
+type Objects = Arc<Mutex<HashMap<String, Bytes>>>;
+
+/// A bucket served over path-style S3 requests on a local port.
+pub struct FakeS3 {
+    objects: Objects,
+    pub storage: S3Storage,
+}
+
+impl FakeS3 {
+    pub async fn start() -> Self {
+        let objects = Objects::default();
+        let router = Router::new()
+            .route(
+                "/{bucket}/{*key}",
+                routing::get(get_object)
+                    .put(put_object)
+                    .delete(delete_object),
+            )
+            .with_state(objects.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Bind the fake S3 server");
+        let address = listener.local_addr().expect("Read the fake S3 address");
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let config = Box::leak(Box::new(S3Config {
+            endpoint: format!("http://{address}"),
+            bucket: BUCKET.to_string(),
+            region: None,
+            access_key: ACCESS_KEY.to_string(),
+            secret_key: "minioadmin".to_string(),
+            path_style: Some(true),
+        }));
+
+        Self {
+            objects,
+            storage: S3Storage::new(config).expect("Build the S3 storage"),
+        }
+    }
+
+    /// The keys of the stored objects, sorted.
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys = self
+            .objects
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+}
+
+/// Rejects requests that are not signed for the bucket's access key or whose
+/// body does not match the signed payload hash, as a real server would.
+fn check_signature(headers: &HeaderMap, body: &[u8]) -> Result<(), StatusCode> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let signed = header("authorization").is_some_and(|authorization| {
+        authorization.starts_with(&format!("AWS4-HMAC-SHA256 Credential={ACCESS_KEY}/"))
+    });
+    let hash = format!("{:x}", Sha256::digest(body));
+    if !signed || header("x-amz-content-sha256") != Some(hash.as_str()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+fn check_bucket(bucket: &str) -> Result<(), StatusCode> {
+    if bucket != BUCKET {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(())
+}
+
+async fn put_object(
+    State(objects): State<Objects>,
+    Path((bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, StatusCode> {
+    check_bucket(&bucket)?;
+    check_signature(&headers, &body)?;
+    objects.lock().unwrap().insert(key, body);
+    Ok(StatusCode::OK)
+}
+
+async fn get_object(
+    State(objects): State<Objects>,
+    Path((bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    check_bucket(&bucket)?;
+    check_signature(&headers, &[])?;
+    let Some(data) = objects.lock().unwrap().get(&key).cloned() else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|range| range.to_str().ok())
+        .and_then(|range| range.strip_prefix("bytes="))
+        .and_then(|range| range.split_once('-'))
+        .and_then(|(start, end)| Some((start.parse::<usize>().ok()?, end.parse::<usize>().ok()?)));
+    match range {
+        Some((start, end)) => {
+            let end = (end + 1).min(data.len());
+            Ok((StatusCode::PARTIAL_CONTENT, data.slice(start..end)).into_response())
+        }
+        None => Ok(data.into_response()),
+    }
+}
+
+async fn delete_object(
+    State(objects): State<Objects>,
+    Path((bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    check_bucket(&bucket)?;
+    check_signature(&headers, &[])?;
+    objects.lock().unwrap().remove(&key);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,386 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use anyhow::{Context, bail};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{
+    Client, Url,
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect,
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, JsonValue,
+    QueryFilter,
+};
+use serde::Serialize;
+use serde_json::json;
+use sha2::Sha256;
+
+use crate::{
+    app::AppState,
+    entity::{delivery_status::DeliveryStatus, prelude::*, sys_webhook, sys_webhook_delivery},
+    event::{Event, Subscriber, UserCreated, UserDeleted, UserDisabled, UserUpdated},
+    queue::{
+        self,
+        webhook::{Deliver, Delivery},
+    },
+};
+
+/// The events webhooks can subscribe to.
+pub const EVENTS: &[&str] = &[
+    UserCreated::NAME,
+    UserUpdated::NAME,
+    UserDisabled::NAME,
+    UserDeleted::NAME,
+];
+
+/// Subscribes a webhook to every event.
+pub const ALL_EVENTS: &str = "*";
+
+/// Sent to a single webhook on request, to check the receiver.
+pub const PING: &str = "ping";
+
+/// How long a receiver may take to respond.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much of a response body is kept in the delivery log.
+const RESPONSE_EXCERPT: usize = 1024;
+
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    Client::builder()
+        .timeout(TIMEOUT)
+        .redirect(redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        // A proxy would resolve receivers by itself, past `PublicResolver`.
+        .no_proxy()
+        .build()
+        .expect("Failed to build the webhook client")
+});
+
+/// Whether `ip` belongs to this host, a private network or a reserved range.
+/// Webhooks are never sent there, so that their URLs cannot reach internal
+/// services such as the cloud metadata endpoint at 169.254.169.254.
+pub fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // "This network", shared address space (carrier-grade NAT),
+                // IETF protocol assignments, benchmarking and reserved.
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (18..20).contains(&b))
+                || a >= 240
+        }
+        IpAddr::V6(ip) => {
+            // IPv4-mapped and -compatible addresses reach the IPv4 host.
+            if let Some(ip) = ip.to_ipv4() {
+                return is_internal(IpAddr::V4(ip));
+            }
+            let [a, b, ..] = ip.segments();
+            ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local, link-local and documentation.
+                || a & 0xfe00 == 0xfc00
+                || a & 0xffc0 == 0xfe80
+                || (a == 0x2001 && b == 0x0db8)
+        }
+    }
+}
+
+/// Resolves `host`, failing unless every address it has is public.
+async fn public_addresses(host: &str) -> anyhow::Result<Vec<SocketAddr>> {
+    let addresses = tokio::net::lookup_host((host, 0))
+        .await
+        .with_context(|| format!("Resolve {host}"))?
+        .collect::<Vec<_>>();
+    if addresses.is_empty() {
+        bail!("{host} has no address");
+    }
+    if let Some(address) = addresses.iter().find(|address| is_internal(address.ip())) {
+        bail!("{host} resolves to the internal address {}", address.ip());
+    }
+    Ok(addresses)
+}
+
+/// Rejects a webhook URL whose host is, or resolves to, an internal address.
+/// Names are resolved again on delivery, by `PublicResolver`, since they may
+/// point elsewhere by then.
+pub async fn check_url(url: &str) -> anyhow::Result<()> {
+    let url = Url::parse(url).context("Parse the URL")?;
+    let host = url.host_str().context("The URL has no host")?;
+    // IPv6 hosts keep their brackets.
+    let Ok(ip) = host.trim_matches(['[', ']']).parse::<IpAddr>() else {
+        return public_addresses(host).await.map(drop);
+    };
+    if is_internal(ip) {
+        bail!("{ip} is an internal address");
+    }
+    Ok(())
+}
+
+/// Connects webhook requests only to public addresses, checking those that
+/// are actually connected to rather than those seen when the webhook was
+/// saved.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses = public_addresses(name.as_str()).await?;
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Whether `webhook` subscribed to `event`.
+pub fn subscribes_to(webhook: &sys_webhook::Model, event: &str) -> bool {
+    webhook.events.as_array().is_some_and(|events| {
+        events
+            .iter()
+            .any(|subscribed| subscribed == event || subscribed == ALL_EVENTS)
+    })
+}
+
+/// Queues a delivery of `event` with `data` to every enabled webhook
+/// subscribed to it. Pass a transaction to deliver the event only if the
+/// change it reports commits.
+pub async fn dispatch<C: ConnectionTrait>(
+    db: &C,
+    event: &str,
+    data: impl Serialize,
+) -> anyhow::Result<()> {
+    let webhooks = SysWebhook::find()
+        .filter(sys_webhook::Column::Enabled.eq(true))
+        .all(db)
+        .await
+        .context("Find webhooks")?
+        .into_iter()
+        .filter(|webhook| subscribes_to(webhook, event))
+        .collect::<Vec<_>>();
+    if webhooks.is_empty() {
+        return Ok(());
+    }
+
+    let data = serde_json::to_value(data).context("Serialize the event")?;
+    for webhook in &webhooks {
+        deliver(db, webhook, event, data.clone()).await?;
+    }
+
+    Ok(())
+}
+
+/// Forwards events to the webhooks subscribed to them, with the event as the
+/// payload's `data`.
+pub struct Forward;
+
+#[async_trait]
+impl<E: Event> Subscriber<E> for Forward {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn durable(&self) -> bool {
+        true
+    }
+
+    async fn handle(&self, state: &AppState, event: &E) -> anyhow::Result<()> {
+        dispatch(&state.db, E::NAME, event).await
+    }
+}
+
+/// Records a delivery of `event` to `webhook` and queues sending it.
+pub async fn deliver<C: ConnectionTrait>(
+    db: &C,
+    webhook: &sys_webhook::Model,
+    event: &str,
+    data: JsonValue,
+) -> anyhow::Result<sys_webhook_delivery::Model> {
+    let delivery = sys_webhook_delivery::ActiveModel {
+        webhook_id: ActiveValue::Set(webhook.id.clone()),
+        event: ActiveValue::Set(event.to_string()),
+        payload: ActiveValue::Set(json!({
+            "event": event,
+            "occurredAt": chrono::Utc::now(),
+            "data": data,
+        })),
+        status: ActiveValue::Set(DeliveryStatus::Pending),
+        attempts: ActiveValue::Set(0),
+        response_status: ActiveValue::Set(None),
+        response_body: ActiveValue::Set(None),
+        error: ActiveValue::Set(None),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .context("Record webhook delivery")?;
+
+    queue::enqueue(
+        db,
+        &Deliver,
+        Delivery {
+            delivery_id: delivery.id.clone(),
+        },
+    )
+    .await?;
+
+    Ok(delivery)
+}
+
+/// The signature of a delivery: the hex HMAC-SHA256, keyed with the webhook
+/// secret, of the timestamp, a `.` and the body. Covering the timestamp lets
+/// receivers reject replayed requests.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// The outcome of one attempt to send a delivery.
+pub struct Attempt {
+    pub response_status: Option<u16>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Attempt {
+    pub fn succeeded(&self) -> bool {
+        self.response_status
+            .is_some_and(|status| (200..300).contains(&status))
+    }
+}
+
+/// POSTs `delivery` to `webhook` once, with headers identifying and signing
+/// it.
+pub async fn send(
+    webhook: &sys_webhook::Model,
+    delivery: &sys_webhook_delivery::Model,
+) -> anyhow::Result<Attempt> {
+    // Addresses given literally bypass `PublicResolver`.
+    if let Err(e) = check_url(&webhook.url).await {
+        return Ok(Attempt {
+            response_status: None,
+            response_body: None,
+            error: Some(format!("{e:#}")),
+        });
+    }
+
+    let body = serde_json::to_vec(&delivery.payload).context("Serialize the delivery")?;
+    let timestamp = chrono::Utc::now().timestamp();
+    let signature = sign(&webhook.secret, timestamp, &body);
+
+    let response = CLIENT
+        .post(&webhook.url)
+        .header("content-type", "application/json")
+        .header("x-webhook-id", &delivery.id)
+        .header("x-webhook-event", &delivery.event)
+        .header("x-webhook-timestamp", timestamp)
+        .header("x-webhook-signature", format!("sha256={signature}"))
+        .body(body)
+        .send()
+        .await;
+
+    Ok(match response {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            Attempt {
+                response_status: Some(status),
+                response_body: Some(body.chars().take(RESPONSE_EXCERPT).collect()),
+                error: None,
+            }
+        }
+        Err(e) => Attempt {
+            response_status: None,
+            response_body: None,
+            error: Some(e.to_string()),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn internal(ip: &str) -> bool {
+        is_internal(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn detects_internal_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(internal(ip), "{ip} should be internal");
+        }
+    }
+
+    #[test]
+    fn allows_public_addresses() {
+        for ip in ["1.1.1.1", "8.8.8.8", "100.128.0.1", "2606:4700::1111"] {
+            assert!(!internal(ip), "{ip} should be public");
+        }
+    }
+
+    #[tokio::test]
+    async fn check_url_rejects_internal_hosts() {
+        assert!(check_url("http://127.0.0.1:8080/hook").await.is_err());
+        assert!(check_url("http://[::1]/hook").await.is_err());
+        assert!(
+            check_url("http://169.254.169.254/latest/meta-data")
+                .await
+                .is_err()
+        );
+        assert!(check_url("http://localhost/hook").await.is_err());
+        assert!(check_url("https://1.1.1.1/hook").await.is_ok());
+    }
+
+    #[test]
+    fn signs_the_timestamp_and_body() {
+        let body = br#"{"event":"user.updated"}"#;
+        assert_eq!(
+            sign("secret", 1_700_000_000, body),
+            "4690b9c375c1bba6500e894e48f64020f112abf27bfbe48461ae8b0a20356ede"
+        );
+        assert_ne!(
+            sign("secret", 1_700_000_001, body),
+            sign("secret", 1_700_000_000, body)
+        );
+        assert_ne!(
+            sign("other", 1_700_000_000, body),
+            sign("secret", 1_700_000_000, body)
+        );
+    }
+}